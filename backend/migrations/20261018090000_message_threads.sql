@@ -0,0 +1,5 @@
+-- DMのスレッド (1階層のみ)。NULLならスレッドの元メッセージ
+ALTER TABLE messages
+    ADD COLUMN parent_message_id UUID REFERENCES messages(id) ON DELETE CASCADE;
+
+CREATE INDEX idx_messages_parent_message_id ON messages(parent_message_id);
@@ -0,0 +1,29 @@
+-- チャットセッション (教員の「履歴クリア」で次のセッションに切り替わる)
+CREATE TABLE room_sessions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    room_id UUID NOT NULL REFERENCES rooms(id) ON DELETE CASCADE,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ended_at TIMESTAMPTZ -- NULLなら進行中
+);
+
+-- 進行中のセッションはルームごとに1つだけ
+CREATE UNIQUE INDEX idx_room_sessions_active ON room_sessions(room_id) WHERE ended_at IS NULL;
+
+-- 既存のルームには進行中のセッションを1つ作り、既存のメッセージをそこにまとめる
+INSERT INTO room_sessions (room_id, started_at)
+SELECT r.id, COALESCE(MIN(m.sent_at), r.created_at)
+FROM rooms r
+LEFT JOIN messages m ON m.room_id = r.id
+GROUP BY r.id, r.created_at;
+
+ALTER TABLE messages
+    ADD COLUMN session_id UUID REFERENCES room_sessions(id) ON DELETE CASCADE;
+
+UPDATE messages m
+SET session_id = s.id
+FROM room_sessions s
+WHERE s.room_id = m.room_id;
+
+ALTER TABLE messages ALTER COLUMN session_id SET NOT NULL;
+
+CREATE INDEX idx_messages_session_id ON messages(session_id);
@@ -0,0 +1,21 @@
+-- 追加教員の指定 (そのセッションの間だけ有効。次のセッションでは再度指定が必要)
+CREATE TABLE session_teachers (
+    session_id UUID NOT NULL REFERENCES room_sessions(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    granted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (session_id, user_id)
+);
+
+-- 既存の追加教員 (作成者以外の TEACHER) は進行中のセッションでの指定に移す
+INSERT INTO session_teachers (session_id, user_id)
+SELECT s.id, rm.user_id
+FROM room_members rm
+JOIN rooms r ON r.id = rm.room_id
+JOIN room_sessions s ON s.room_id = rm.room_id AND s.ended_at IS NULL
+WHERE rm.role = 'TEACHER' AND rm.user_id <> r.owner_id;
+
+-- room_members.role は恒久的な権限 (作成者のみ TEACHER) として扱う
+UPDATE room_members rm
+SET role = 'STUDENT'
+FROM rooms r
+WHERE r.id = rm.room_id AND rm.role = 'TEACHER' AND rm.user_id <> r.owner_id;
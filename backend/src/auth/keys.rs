@@ -0,0 +1,310 @@
+use async_trait::async_trait;
+use jsonwebtoken::{jwk::JwkSet, DecodingKey};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+
+/// Firebase Auth (securetoken) の公開鍵を配布している Google のエンドポイント
+pub const GOOGLE_SECURETOKEN_JWKS_URL: &str =
+    "https://www.googleapis.com/service_accounts/v1/jwk/securetoken@system.gserviceaccount.com";
+
+/// Cache-Control が付いていない場合のキャッシュ期間
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// 鍵の取得にかける時間の上限
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 取得に失敗したとき、古い鍵を使い続けて次に取り直すまでの間隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 鍵ソースから取得した JWK Set と、そのキャッシュ期間
+pub struct FetchedKeys {
+    pub jwks: JwkSet,
+    pub max_age: Option<Duration>,
+}
+
+/// 公開鍵の取得元。本番は Google の HTTP エンドポイント、テストではファイルやローカルのHTTPサーバーに差し替える
+#[async_trait]
+pub trait KeySource: Send + Sync {
+    async fn fetch(&self) -> Result<FetchedKeys, String>;
+}
+
+/// HTTP で JWK Set を取得する鍵ソース (Cache-Control の max-age を尊重する)
+pub struct HttpKeySource {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl HttpKeySource {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(FETCH_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client"),
+            url: url.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl KeySource for HttpKeySource {
+    async fn fetch(&self) -> Result<FetchedKeys, String> {
+        let res = self
+            .client
+            .get(&self.url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Failed to fetch JWKS: {}", e))?;
+
+        let max_age = res
+            .headers()
+            .get(reqwest::header::CACHE_CONTROL)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_max_age);
+
+        let jwks = res
+            .json::<JwkSet>()
+            .await
+            .map_err(|e| format!("Failed to parse JWKS: {}", e))?;
+
+        Ok(FetchedKeys { jwks, max_age })
+    }
+}
+
+/// ローカルファイルから JWK Set を読み込む鍵ソース (オフライン検証用)
+pub struct FileKeySource {
+    path: PathBuf,
+}
+
+impl FileKeySource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl KeySource for FileKeySource {
+    async fn fetch(&self) -> Result<FetchedKeys, String> {
+        let raw = tokio::fs::read_to_string(&self.path)
+            .await
+            .map_err(|e| format!("Failed to read JWKS file: {}", e))?;
+        let jwks = serde_json::from_str::<JwkSet>(&raw)
+            .map_err(|e| format!("Failed to parse JWKS file: {}", e))?;
+
+        Ok(FetchedKeys {
+            jwks,
+            max_age: None,
+        })
+    }
+}
+
+/// `Cache-Control: public, max-age=19204, must-revalidate` から max-age を取り出す
+fn parse_max_age(cache_control: &str) -> Option<Duration> {
+    cache_control
+        .split(',')
+        .filter_map(|directive| directive.trim().strip_prefix("max-age="))
+        .find_map(|secs| secs.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+struct CachedKeys {
+    keys: HashMap<String, DecodingKey>,
+    expires_at: Instant,
+}
+
+/// kid ごとに DecodingKey をキャッシュする鍵ストア
+pub struct KeyStore {
+    source: Box<dyn KeySource>,
+    cache: RwLock<Option<CachedKeys>>,
+    /// 取得を同時に1つだけ走らせる (取得中もキャッシュは読める)
+    refresh: Mutex<()>,
+}
+
+impl KeyStore {
+    pub fn new(source: impl KeySource + 'static) -> Self {
+        Self {
+            source: Box::new(source),
+            cache: RwLock::new(None),
+            refresh: Mutex::new(()),
+        }
+    }
+
+    /// kid に対応する鍵を返す。キャッシュが切れていれば取り直し、それでも無ければ None
+    /// 取り直しに失敗したときは、古い鍵があればそれを使い続ける
+    pub async fn get(&self, kid: &str) -> Result<Option<DecodingKey>, String> {
+        if let Some(found) = self.cached(kid).await {
+            return Ok(found);
+        }
+
+        let _refresh = self.refresh.lock().await;
+        // ロック待ちの間に他のリクエストが更新済みかもしれない
+        if let Some(found) = self.cached(kid).await {
+            return Ok(found);
+        }
+
+        match self.source.fetch().await {
+            Ok(fetched) => {
+                let keys = fetched
+                    .jwks
+                    .keys
+                    .iter()
+                    .filter_map(|jwk| {
+                        let kid = jwk.common.key_id.clone()?;
+                        let key = DecodingKey::from_jwk(jwk).ok()?;
+                        Some((kid, key))
+                    })
+                    .collect::<HashMap<_, _>>();
+
+                let found = keys.get(kid).cloned();
+                *self.cache.write().await = Some(CachedKeys {
+                    keys,
+                    expires_at: Instant::now() + fetched.max_age.unwrap_or(DEFAULT_MAX_AGE),
+                });
+                Ok(found)
+            }
+            Err(e) => {
+                let mut cache = self.cache.write().await;
+                let Some(stale) = cache.as_mut() else {
+                    return Err(e);
+                };
+                eprintln!("{} (keeping cached keys)", e);
+                // リクエストのたびに取り直さないよう、しばらくは古い鍵で検証する
+                stale.expires_at = Instant::now() + RETRY_INTERVAL;
+                Ok(stale.keys.get(kid).cloned())
+            }
+        }
+    }
+
+    /// 有効期限内のキャッシュがあれば、その中から kid の鍵を探す
+    async fn cached(&self, kid: &str) -> Option<Option<DecodingKey>> {
+        let cache = self.cache.read().await;
+        cache
+            .as_ref()
+            .filter(|c| c.expires_at > Instant::now())
+            .map(|c| c.keys.get(kid).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct CountingSource {
+        calls: Arc<AtomicUsize>,
+        max_age: Option<Duration>,
+        /// 2回目以降の取得を失敗させる
+        fail_after_first: bool,
+    }
+
+    #[async_trait]
+    impl KeySource for CountingSource {
+        async fn fetch(&self) -> Result<FetchedKeys, String> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) > 0 && self.fail_after_first {
+                return Err("Failed to fetch JWKS: unavailable".to_string());
+            }
+            let jwks = FileKeySource::new("testdata/test_jwks.json")
+                .fetch()
+                .await?
+                .jwks;
+            Ok(FetchedKeys {
+                jwks,
+                max_age: self.max_age,
+            })
+        }
+    }
+
+    #[test]
+    fn parses_max_age_from_cache_control() {
+        assert_eq!(
+            parse_max_age("public, max-age=19204, must-revalidate, no-transform"),
+            Some(Duration::from_secs(19204))
+        );
+        assert_eq!(parse_max_age("no-cache"), None);
+    }
+
+    #[tokio::test]
+    async fn caches_keys_until_max_age() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let store = KeyStore::new(CountingSource {
+            calls: calls.clone(),
+            max_age: Some(Duration::from_secs(3600)),
+            fail_after_first: false,
+        });
+
+        assert!(store.get("test-key-1").await.unwrap().is_some());
+        assert!(store.get("test-key-1").await.unwrap().is_some());
+        assert!(store.get("unknown-kid").await.unwrap().is_none());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn refetches_after_expiry() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let store = KeyStore::new(CountingSource {
+            calls: calls.clone(),
+            max_age: Some(Duration::ZERO),
+            fail_after_first: false,
+        });
+
+        store.get("test-key-1").await.unwrap();
+        store.get("test-key-1").await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn keeps_stale_keys_when_refresh_fails() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let store = KeyStore::new(CountingSource {
+            calls: calls.clone(),
+            max_age: Some(Duration::ZERO),
+            fail_after_first: true,
+        });
+
+        assert!(store.get("test-key-1").await.unwrap().is_some());
+        assert!(store.get("test-key-1").await.unwrap().is_some());
+        // 失敗した直後はリクエストのたびに取り直さない
+        assert!(store.get("test-key-1").await.unwrap().is_some());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn fails_when_nothing_is_cached() {
+        let calls = Arc::new(AtomicUsize::new(1));
+        let store = KeyStore::new(CountingSource {
+            calls,
+            max_age: None,
+            fail_after_first: true,
+        });
+
+        assert!(store.get("test-key-1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn http_source_reads_local_stand_in() {
+        use axum::{http::header, routing::get, Router};
+
+        let jwks = std::fs::read_to_string("testdata/test_jwks.json").unwrap();
+        let app = Router::new().route(
+            "/jwks",
+            get(move || async move { ([(header::CACHE_CONTROL, "public, max-age=120")], jwks) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let fetched = HttpKeySource::new(format!("http://{}/jwks", addr))
+            .fetch()
+            .await
+            .unwrap();
+        assert_eq!(fetched.max_age, Some(Duration::from_secs(120)));
+        assert_eq!(
+            fetched.jwks.keys[0].common.key_id.as_deref(),
+            Some("test-key-1")
+        );
+    }
+}
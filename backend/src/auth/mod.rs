@@ -0,0 +1,210 @@
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+};
+use jsonwebtoken::{decode, decode_header, Algorithm, Validation};
+use serde::Deserialize;
+use std::env;
+use std::sync::Arc;
+
+use crate::error::AppError;
+
+pub mod keys;
+
+use keys::{FileKeySource, HttpKeySource, KeyStore, GOOGLE_SECURETOKEN_JWKS_URL};
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)] // トークンに含まれるクレームはすべて保持しておく
+pub struct Claims {
+    pub sub: String,
+    pub email: Option<String>,
+    pub name: Option<String>,
+    pub picture: Option<String>,
+    pub email_verified: Option<bool>,
+    pub exp: usize,
+    pub iat: usize,
+    pub aud: String,
+    pub iss: String,
+}
+
+/// Firebase ID トークンの検証器 (署名・aud・iss・exp を検証する)
+pub struct FirebaseAuth {
+    project_id: String,
+    keys: KeyStore,
+}
+
+impl FirebaseAuth {
+    pub fn new(project_id: impl Into<String>, keys: KeyStore) -> Self {
+        Self {
+            project_id: project_id.into(),
+            keys,
+        }
+    }
+
+    /// 環境変数から検証器を組み立てる
+    /// `FIREBASE_JWKS_FILE` / `FIREBASE_JWKS_URL` が指定されていれば Google の代わりにそちらから鍵を読む
+    pub fn from_env() -> Self {
+        let project_id = env::var("FIREBASE_PROJECT_ID").expect("FIREBASE_PROJECT_ID must be set");
+
+        let keys = match (
+            env::var("FIREBASE_JWKS_FILE"),
+            env::var("FIREBASE_JWKS_URL"),
+        ) {
+            (Ok(path), _) if !path.is_empty() => KeyStore::new(FileKeySource::new(path)),
+            (_, Ok(url)) if !url.is_empty() => KeyStore::new(HttpKeySource::new(url)),
+            _ => KeyStore::new(HttpKeySource::new(GOOGLE_SECURETOKEN_JWKS_URL)),
+        };
+
+        Self::new(project_id, keys)
+    }
+
+    /// トークン文字列を受け取って検証する
+    pub async fn verify_token(&self, token: &str) -> Result<Claims, AppError> {
+        let header = decode_header(token)
+            .map_err(|_| AppError::Unauthorized("Invalid JWT header".to_string()))?;
+
+        if header.alg != Algorithm::RS256 {
+            return Err(AppError::Unauthorized(
+                "Unsupported JWT algorithm".to_string(),
+            ));
+        }
+
+        let kid = header
+            .kid
+            .ok_or(AppError::Unauthorized("Missing JWT kid".to_string()))?;
+
+        // 未知の kid は弾く
+        let key = self
+            .keys
+            .get(&kid)
+            .await
+            .map_err(AppError::Internal)?
+            .ok_or(AppError::Unauthorized("Unknown JWT kid".to_string()))?;
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[&self.project_id]);
+        validation.set_issuer(&[format!(
+            "https://securetoken.google.com/{}",
+            self.project_id
+        )]);
+        validation.set_required_spec_claims(&["exp", "iat", "aud", "iss", "sub"]);
+
+        let token_data = decode::<Claims>(token, &key, &validation)
+            .map_err(|e| AppError::Unauthorized(format!("JWT validation failed: {}", e)))?;
+
+        if token_data.claims.sub.is_empty() {
+            return Err(AppError::Unauthorized("Empty JWT subject".to_string()));
+        }
+
+        Ok(token_data.claims)
+    }
+}
+
+pub struct AuthUser(pub Claims);
+
+impl<S> FromRequestParts<S> for AuthUser
+where
+    S: Send + Sync,
+    Arc<FirebaseAuth>: FromRef<S>,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let auth_header = parts
+            .headers
+            .get("Authorization")
+            .and_then(|value| value.to_str().ok())
+            .ok_or(AppError::Unauthorized(
+                "Missing Authorization header".to_string(),
+            ))?;
+
+        let Some(token) = auth_header.strip_prefix("Bearer ") else {
+            return Err(AppError::Unauthorized(
+                "Invalid Authorization header format".to_string(),
+            ));
+        };
+
+        // 🌟 切り出した関数を呼び出すだけ！
+        let auth = Arc::<FirebaseAuth>::from_ref(state);
+        let claims = auth.verify_token(token).await?;
+        Ok(AuthUser(claims))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    const PROJECT_ID: &str = "axon-test";
+
+    fn verifier() -> FirebaseAuth {
+        FirebaseAuth::new(
+            PROJECT_ID,
+            KeyStore::new(FileKeySource::new("testdata/test_jwks.json")),
+        )
+    }
+
+    fn sign(kid: &str, claims: serde_json::Value) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(kid.to_string());
+        let key = EncodingKey::from_rsa_pem(include_bytes!("../../testdata/test_rsa_private.pem"))
+            .unwrap();
+        encode(&header, &claims, &key).unwrap()
+    }
+
+    fn claims(iss: &str) -> serde_json::Value {
+        let now = chrono::Utc::now().timestamp();
+        json!({
+            "sub": "firebase-uid",
+            "name": "Test User",
+            "exp": now + 3600,
+            "iat": now,
+            "aud": PROJECT_ID,
+            "iss": iss,
+        })
+    }
+
+    #[tokio::test]
+    async fn accepts_token_signed_by_known_key() {
+        let token = sign(
+            "test-key-1",
+            claims("https://securetoken.google.com/axon-test"),
+        );
+        let claims = verifier().verify_token(&token).await.unwrap();
+        assert_eq!(claims.sub, "firebase-uid");
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_kid() {
+        let token = sign(
+            "other-key",
+            claims("https://securetoken.google.com/axon-test"),
+        );
+        let error = verifier().verify_token(&token).await.unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_issuer() {
+        let token = sign(
+            "test-key-1",
+            claims("https://securetoken.google.com/another-project"),
+        );
+        assert!(verifier().verify_token(&token).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_forged_signature() {
+        let token = sign(
+            "test-key-1",
+            claims("https://securetoken.google.com/axon-test"),
+        );
+        // 署名部分を改ざんする
+        let (body, _) = token.rsplit_once('.').unwrap();
+        let forged = format!("{}.{}", body, "c2lnbmF0dXJl");
+        assert!(verifier().verify_token(&forged).await.is_err());
+    }
+}
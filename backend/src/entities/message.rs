@@ -3,9 +3,11 @@ use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
 use super::room::RoomId;
+use super::room_session::RoomSessionId;
 use super::user::UserId;
 
-#[derive(Clone, Debug, PartialEq, Eq, DeriveValueType, Serialize, Deserialize, TS)]
+/// UUIDv7 なので値の大小が送信順と一致し、並び替えやページングのキーにそのまま使える
+#[derive(Clone, Debug, Hash, PartialEq, Eq, DeriveValueType, Serialize, Deserialize, TS)]
 #[ts(export, export_to = "../../frontend/types/generated/branded_types.ts")]
 pub struct MessageId(pub uuid::Uuid);
 
@@ -30,6 +32,8 @@ pub struct Model {
     pub recipient_id: Option<UserId>, // DM用の宛先 (null許容)
     pub is_dm: bool,
     pub sent_at: DateTimeWithTimeZone,
+    pub parent_message_id: Option<MessageId>, // スレッドの返信なら元メッセージ (DMのみ・1階層)
+    pub session_id: RoomSessionId, // 送信時に進行中だったセッション
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -55,4 +59,11 @@ impl Related<super::room::Entity> for Entity {
     }
 }
 
+// 送信者 (User) とのリレーション
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Sender.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
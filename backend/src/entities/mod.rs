@@ -2,4 +2,7 @@ pub mod prelude;
 pub mod user;
 pub mod room;
 pub mod room_member;
-pub mod message;
\ No newline at end of file
+pub mod message;
+pub mod reaction;
+pub mod room_session;
+pub mod session_teacher;
\ No newline at end of file
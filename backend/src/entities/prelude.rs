@@ -4,3 +4,6 @@ pub use super::room::Entity as Room;
 pub use super::user::Entity as User;
 pub use super::room_member::Entity as RoomMember;
 pub use super::message::Entity as Message;
+pub use super::reaction::Entity as Reaction;
+pub use super::room_session::Entity as RoomSession;
+pub use super::session_teacher::Entity as SessionTeacher;
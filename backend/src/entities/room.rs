@@ -22,7 +22,7 @@ impl RoomSlug {
     /// 文字列を受け取り、バリデーションと正規化（小文字化）を行ってから RoomSlug を返す
     pub fn new(slug: String) -> Result<Self, &'static str> {
         let len = slug.chars().count();
-        if len < 4 || len > 16 {
+        if !(4..=16).contains(&len) {
             return Err("Slug must be between 4 and 16 characters");
         }
 
@@ -63,6 +63,8 @@ pub enum Relation {
         to = "super::user::Column::Id"
     )]
     Owner,
+    #[sea_orm(has_many = "super::room_member::Entity")]
+    Members,
 }
 
 impl Related<super::user::Entity> for Entity {
@@ -71,4 +73,11 @@ impl Related<super::user::Entity> for Entity {
     }
 }
 
+// RoomMemberとのリレーション (参加しているルームの一覧で使う)
+impl Related<super::room_member::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Members.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,47 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use super::room::RoomId;
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, DeriveValueType, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../frontend/types/generated/branded_types.ts")]
+pub struct RoomSessionId(pub uuid::Uuid);
+
+impl sea_orm::TryFromU64 for RoomSessionId {
+    fn try_from_u64(_: u64) -> Result<Self, sea_orm::DbErr> {
+        Err(sea_orm::DbErr::Custom(
+            "Cannot convert u64 to RoomSessionId (using UUID)".into(),
+        ))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, TS)]
+#[sea_orm(table_name = "room_sessions")]
+#[ts(export, export_to = "../../frontend/types/generated/room_session.ts", rename = "RoomSession")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: RoomSessionId,
+    pub room_id: RoomId,
+    pub started_at: DateTimeWithTimeZone,
+    pub ended_at: Option<DateTimeWithTimeZone>, // NULLなら進行中 (ルームごとに1つだけ)
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::room::Entity",
+        from = "Column::RoomId",
+        to = "super::room::Column::Id"
+    )]
+    Room,
+}
+
+// Roomとのリレーション
+impl Related<super::room::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Room.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
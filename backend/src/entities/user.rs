@@ -5,7 +5,7 @@ use ts_rs::TS;
 // --- NewType Pattern ---
 // これにより、UserId はただの Uuid ではなくなる。
 // RoomId と取り違えるとコンパイルエラーになる。
-#[derive(Clone, Debug, PartialEq, Eq, DeriveValueType, Serialize, Deserialize, TS)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, DeriveValueType, Serialize, Deserialize, TS)]
 #[ts(export, export_to = "../../frontend/types/generated/branded_types.ts")]
 pub struct UserId(pub uuid::Uuid);
 
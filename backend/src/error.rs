@@ -0,0 +1,214 @@
+//! REST API のエラー
+//! クライアントには機械判別用のコードとメッセージを JSON で返し、内部エラーの詳細はログにだけ残す
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use std::fmt;
+use ts_rs::TS;
+
+/// エラーの機械判別用コード (フロントエンドはこれで分岐する)
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[ts(export, export_to = "../../frontend/types/generated/error_code.ts")]
+pub enum ErrorCode {
+    /// パス・クエリ・ボディの形式が正しくない
+    BadRequest,
+    /// ボディは JSON として読めたが、項目が足りない・型が違う
+    UnprocessableEntity,
+    /// Content-Type が JSON ではない
+    UnsupportedMediaType,
+    /// ボディが大きすぎる
+    PayloadTooLarge,
+    /// 認証トークンや接続用チケットがない・無効
+    Unauthorized,
+    RoomNotFound,
+    /// ルームのメンバーではない
+    NotAMember,
+    /// 指定した slug はすでに使われている
+    SlugTaken,
+    /// slug の形式が正しくない
+    InvalidSlug,
+    /// ルームが終了している
+    RoomEnded,
+    /// ルーム作成者だけが行える操作
+    OwnerOnly,
+    /// 教員だけが行える操作
+    TeacherOnly,
+    SessionNotFound,
+    MessageNotFound,
+    MemberNotFound,
+    /// ルーム作成者を学生にはできない
+    CannotDemoteOwner,
+    /// 返信先にできないメッセージ
+    InvalidReplyParent,
+    /// サーバー内部のエラー (詳細はサーバーのログにだけ残る)
+    Internal,
+}
+
+/// エラー時のレスポンスボディ
+#[derive(Serialize, Debug, TS)]
+#[ts(export, export_to = "../../frontend/types/generated/error_response.ts")]
+pub struct ErrorResponse {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub enum AppError {
+    /// リクエストを読み取れなかった理由を添える
+    BadRequest(String),
+    UnprocessableEntity(String),
+    UnsupportedMediaType(String),
+    PayloadTooLarge(String),
+    Unauthorized(String),
+    RoomNotFound,
+    NotAMember,
+    SlugTaken,
+    InvalidSlug(String),
+    RoomEnded,
+    /// 行おうとした操作の説明を添える
+    OwnerOnly(&'static str),
+    TeacherOnly(&'static str),
+    SessionNotFound,
+    MessageNotFound,
+    MemberNotFound,
+    CannotDemoteOwner,
+    InvalidReplyParent(String),
+    /// クライアントには返さず、ログにだけ残す
+    Internal(String),
+}
+
+impl AppError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::BadRequest(_) => ErrorCode::BadRequest,
+            AppError::UnprocessableEntity(_) => ErrorCode::UnprocessableEntity,
+            AppError::UnsupportedMediaType(_) => ErrorCode::UnsupportedMediaType,
+            AppError::PayloadTooLarge(_) => ErrorCode::PayloadTooLarge,
+            AppError::Unauthorized(_) => ErrorCode::Unauthorized,
+            AppError::RoomNotFound => ErrorCode::RoomNotFound,
+            AppError::NotAMember => ErrorCode::NotAMember,
+            AppError::SlugTaken => ErrorCode::SlugTaken,
+            AppError::InvalidSlug(_) => ErrorCode::InvalidSlug,
+            AppError::RoomEnded => ErrorCode::RoomEnded,
+            AppError::OwnerOnly(_) => ErrorCode::OwnerOnly,
+            AppError::TeacherOnly(_) => ErrorCode::TeacherOnly,
+            AppError::SessionNotFound => ErrorCode::SessionNotFound,
+            AppError::MessageNotFound => ErrorCode::MessageNotFound,
+            AppError::MemberNotFound => ErrorCode::MemberNotFound,
+            AppError::CannotDemoteOwner => ErrorCode::CannotDemoteOwner,
+            AppError::InvalidReplyParent(_) => ErrorCode::InvalidReplyParent,
+            AppError::Internal(_) => ErrorCode::Internal,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::RoomNotFound
+            | AppError::SessionNotFound
+            | AppError::MessageNotFound
+            | AppError::MemberNotFound => StatusCode::NOT_FOUND,
+            AppError::NotAMember
+            | AppError::RoomEnded
+            | AppError::OwnerOnly(_)
+            | AppError::TeacherOnly(_) => StatusCode::FORBIDDEN,
+            AppError::SlugTaken => StatusCode::CONFLICT,
+            AppError::UnprocessableEntity(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::BadRequest(_)
+            | AppError::InvalidSlug(_)
+            | AppError::CannotDemoteOwner
+            | AppError::InvalidReplyParent(_) => StatusCode::BAD_REQUEST,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// クライアントに返すメッセージ
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::BadRequest(reason)
+            | AppError::UnprocessableEntity(reason)
+            | AppError::UnsupportedMediaType(reason)
+            | AppError::PayloadTooLarge(reason) => write!(f, "{}", reason),
+            AppError::Unauthorized(reason) => write!(f, "{}", reason),
+            AppError::RoomNotFound => write!(f, "Room not found"),
+            AppError::NotAMember => write!(f, "Not a member of this room"),
+            AppError::SlugTaken => write!(f, "This room ID is already taken"),
+            AppError::InvalidSlug(reason) => write!(f, "{}", reason),
+            AppError::RoomEnded => write!(f, "This room has ended"),
+            AppError::OwnerOnly(action) => write!(f, "Only the room owner can {}", action),
+            AppError::TeacherOnly(action) => write!(f, "Only teachers can {}", action),
+            AppError::SessionNotFound => write!(f, "Session not found"),
+            AppError::MessageNotFound => write!(f, "Message not found"),
+            AppError::MemberNotFound => write!(f, "Member not found"),
+            AppError::CannotDemoteOwner => write!(f, "Cannot demote the room owner"),
+            AppError::InvalidReplyParent(reason) => write!(f, "{}", reason),
+            AppError::Internal(_) => write!(f, "Internal server error"),
+        }
+    }
+}
+
+impl From<sea_orm::DbErr> for AppError {
+    fn from(e: sea_orm::DbErr) -> Self {
+        AppError::Internal(e.to_string())
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        if let AppError::Internal(detail) = &self {
+            eprintln!("Internal error: {}", detail);
+        }
+
+        let body = ErrorResponse {
+            code: self.code(),
+            message: self.to_string(),
+        };
+        (self.status(), Json(body)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serializes_codes_in_screaming_snake_case() {
+        let value = serde_json::to_value(ErrorResponse {
+            code: AppError::NotAMember.code(),
+            message: AppError::NotAMember.to_string(),
+        })
+        .unwrap();
+        assert_eq!(
+            value,
+            json!({ "code": "NOT_A_MEMBER", "message": "Not a member of this room" })
+        );
+    }
+
+    #[test]
+    fn hides_internal_details_from_clients() {
+        let error = AppError::from(sea_orm::DbErr::Custom("connection refused".to_string()));
+        assert_eq!(error.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(error.code(), ErrorCode::Internal);
+        assert_eq!(error.to_string(), "Internal server error");
+    }
+
+    #[test]
+    fn maps_errors_to_status_codes() {
+        assert_eq!(AppError::RoomNotFound.status(), StatusCode::NOT_FOUND);
+        assert_eq!(AppError::SlugTaken.status(), StatusCode::CONFLICT);
+        assert_eq!(
+            AppError::OwnerOnly("delete the room").to_string(),
+            "Only the room owner can delete the room"
+        );
+    }
+}
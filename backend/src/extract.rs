@@ -0,0 +1,188 @@
+//! リクエストの取り出し
+//! axum の extractor を包み、失敗したときもプレーンテキストではなく AppError の JSON で返す
+
+use axum::{
+    extract::{FromRequest, FromRequestParts, Request},
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::AppError;
+
+/// axum の rejection を、同じステータスの AppError にする
+fn rejected(status: StatusCode, reason: String) -> AppError {
+    match status {
+        StatusCode::UNPROCESSABLE_ENTITY => AppError::UnprocessableEntity(reason),
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => AppError::UnsupportedMediaType(reason),
+        StatusCode::PAYLOAD_TOO_LARGE => AppError::PayloadTooLarge(reason),
+        // パスの定義とハンドラーの引数が合っていないなど、サーバー側の誤り
+        status if status.is_server_error() => AppError::Internal(reason),
+        _ => AppError::BadRequest(reason),
+    }
+}
+
+/// パスパラメーター (ID の形式が正しくないなどで読めなければ BAD_REQUEST)
+pub struct Path<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for Path<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Path(value) = axum::extract::Path::from_request_parts(parts, state)
+            .await
+            .map_err(|e| rejected(e.status(), e.body_text()))?;
+        Ok(Path(value))
+    }
+}
+
+/// クエリパラメーター (読めなければ BAD_REQUEST)
+pub struct Query<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Query(value) = axum::extract::Query::from_request_parts(parts, state)
+            .await
+            .map_err(|e| rejected(e.status(), e.body_text()))?;
+        Ok(Query(value))
+    }
+}
+
+/// JSON のリクエストボディ。レスポンスにもそのまま使える
+/// 読めなければ axum と同じステータス (400 / 413 / 415 / 422) で返す
+pub struct Json<T>(pub T);
+
+impl<T, S> FromRequest<S> for Json<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::Json(value) = axum::Json::from_request(req, state)
+            .await
+            .map_err(|e| rejected(e.status(), e.body_text()))?;
+        Ok(Json(value))
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::user::UserId;
+    use axum::{routing::post, Router};
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Limit {
+        limit: Option<u64>,
+    }
+
+    #[derive(Deserialize)]
+    struct Body {
+        name: String,
+    }
+
+    async fn handler(
+        Path((slug, user_id)): Path<(String, UserId)>,
+        Query(query): Query<Limit>,
+        Json(body): Json<Body>,
+    ) -> Json<String> {
+        Json(format!(
+            "{} {:?} {:?} {}",
+            slug, user_id, query.limit, body.name
+        ))
+    }
+
+    #[tokio::test]
+    async fn rejections_are_json_bad_requests() {
+        let app = Router::new().route("/rooms/{slug}/members/{user_id}", post(handler));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let client = reqwest::Client::new();
+        let url = |path: &str| format!("http://{}/rooms/algebra1/members/{}", addr, path);
+        let valid_id = uuid::Uuid::now_v7().to_string();
+        let too_large = "A".repeat(3 * 1024 * 1024);
+        let cases = [
+            // ID が UUID ではない
+            (
+                client
+                    .post(url("not-a-uuid"))
+                    .json(&serde_json::json!({ "name": "A" })),
+                reqwest::StatusCode::BAD_REQUEST,
+                "BAD_REQUEST",
+            ),
+            // クエリの型が違う
+            (
+                client
+                    .post(url(&format!("{}?limit=many", valid_id)))
+                    .json(&serde_json::json!({ "name": "A" })),
+                reqwest::StatusCode::BAD_REQUEST,
+                "BAD_REQUEST",
+            ),
+            // ボディが JSON として壊れている
+            (
+                client
+                    .post(url(&valid_id))
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body("{"),
+                reqwest::StatusCode::BAD_REQUEST,
+                "BAD_REQUEST",
+            ),
+            // ボディの項目が足りない
+            (
+                client.post(url(&valid_id)).json(&serde_json::json!({})),
+                reqwest::StatusCode::UNPROCESSABLE_ENTITY,
+                "UNPROCESSABLE_ENTITY",
+            ),
+            // Content-Type がない
+            (
+                client.post(url(&valid_id)).body(r#"{"name":"A"}"#),
+                reqwest::StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "UNSUPPORTED_MEDIA_TYPE",
+            ),
+            // ボディが上限 (2MB) を超えている
+            (
+                client
+                    .post(url(&valid_id))
+                    .json(&serde_json::json!({ "name": too_large })),
+                reqwest::StatusCode::PAYLOAD_TOO_LARGE,
+                "PAYLOAD_TOO_LARGE",
+            ),
+        ];
+
+        for (request, status, code) in cases {
+            let res = request.send().await.unwrap();
+            assert_eq!(res.status(), status);
+            let body = res.json::<serde_json::Value>().await.unwrap();
+            assert_eq!(body["code"], code);
+        }
+
+        let res = client
+            .post(url(&valid_id))
+            .json(&serde_json::json!({ "name": "A" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+    }
+}
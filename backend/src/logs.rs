@@ -0,0 +1,367 @@
+//! チャットログの CSV ダウンロード (ルーム作成者のみ)
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use futures_util::{stream, StreamExt};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use std::collections::HashMap;
+
+use crate::auth::AuthUser;
+use crate::entities::{message, room, room_session, user};
+use crate::error::AppError;
+use crate::extract::Path;
+use crate::messages;
+use crate::stamps::SYSTEM_STAMPS;
+use crate::ws::protocol::ReactionSummary;
+use crate::{find_room_membership, sync_user, AppState};
+
+/// Excel が UTF-8 として開くための BOM
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+/// 1回の DB 読み込みで CSV にする件数
+const PAGE_SIZE: u64 = 500;
+
+const HEADER: [&str; 8] = [
+    "タイムスタンプ",
+    "セッション開始",
+    "送信者",
+    "送信先",
+    "メッセージ",
+    "メッセージID",
+    "返信先メッセージID",
+    "スタンプ",
+];
+
+/// CSV の1行
+struct LogRow {
+    sent_at: String,
+    session_started_at: String,
+    sender: String,
+    recipient: String,
+    content: String,
+    message_id: String,
+    parent_message_id: String,
+    stamps: String,
+}
+
+/// セッションのログを CSV で返すハンドラ
+pub async fn session_log_handler(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path((slug, session_id)): Path<(String, room_session::RoomSessionId)>,
+) -> Result<Response, AppError> {
+    let target_room = find_owned_room(&state, &claims, &slug).await?;
+
+    let session = room_session::Entity::find_by_id(session_id)
+        .filter(room_session::Column::RoomId.eq(target_room.id.clone()))
+        .one(&state.conn)
+        .await?
+        .ok_or(AppError::SessionNotFound)?;
+
+    let filename = format!(
+        "{}-{}.csv",
+        target_room.slug,
+        session.started_at.format("%Y%m%d-%H%M")
+    );
+    let sessions = HashMap::from([(session.id.0, session.started_at.to_rfc3339())]);
+
+    Ok(csv_response(
+        filename,
+        state.conn,
+        target_room.id,
+        Some(session.id),
+        sessions,
+    ))
+}
+
+/// ルームのすべてのセッションのログを CSV で返すハンドラ
+pub async fn room_log_handler(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(slug): Path<String>,
+) -> Result<Response, AppError> {
+    let target_room = find_owned_room(&state, &claims, &slug).await?;
+
+    let sessions = room_session::Entity::find()
+        .filter(room_session::Column::RoomId.eq(target_room.id.clone()))
+        .all(&state.conn)
+        .await?
+        .into_iter()
+        .map(|s| (s.id.0, s.started_at.to_rfc3339()))
+        .collect::<HashMap<_, _>>();
+
+    let filename = format!("{}-all.csv", target_room.slug);
+
+    Ok(csv_response(
+        filename,
+        state.conn,
+        target_room.id,
+        None,
+        sessions,
+    ))
+}
+
+/// ログをダウンロードできるのはルーム作成者だけ
+async fn find_owned_room(
+    state: &AppState,
+    claims: &crate::auth::Claims,
+    slug: &str,
+) -> Result<room::Model, AppError> {
+    let user_id = sync_user(&state.conn, claims).await?;
+
+    let (target_room, _) = find_room_membership(&state.conn, slug, &user_id).await?;
+    if target_room.owner_id != user_id {
+        return Err(AppError::OwnerOnly("download chat logs"));
+    }
+
+    Ok(target_room)
+}
+
+/// メッセージを古い順に少しずつ読みながら CSV を流すレスポンスを作る
+/// `session_id` が None ならルームのすべてのセッションが対象
+fn csv_response(
+    filename: String,
+    conn: DatabaseConnection,
+    room_id: room::RoomId,
+    session_id: Option<room_session::RoomSessionId>,
+    sessions: HashMap<uuid::Uuid, String>,
+) -> Response {
+    // 1つ目のチャンクは BOM とヘッダー行
+    let first = Some(Ok::<_, sea_orm::DbErr>(encode(&[], true)));
+
+    // 以降は前のページの最後の id から続きを読む
+    let pages = stream::try_unfold(Some(None), move |cursor| {
+        let (conn, room_id, session_id, sessions) = (
+            conn.clone(),
+            room_id.clone(),
+            session_id.clone(),
+            sessions.clone(),
+        );
+        async move {
+            let Some(after) = cursor else {
+                return Ok(None);
+            };
+
+            let rows = load_page(&conn, &room_id, session_id.as_ref(), after, &sessions)
+                .await
+                .inspect_err(|e| eprintln!("Failed to export chat log: {}", e))?;
+
+            if rows.is_empty() {
+                return Ok(None);
+            }
+
+            let next =
+                (rows.len() as u64 == PAGE_SIZE).then(|| rows.last().map(|(id, _)| id.clone()));
+            let rows = rows.into_iter().map(|(_, row)| row).collect::<Vec<_>>();
+
+            Ok(Some((encode(&rows, false), next)))
+        }
+    });
+
+    let body = Body::from_stream(stream::iter(first).chain(pages));
+
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// `after` より後のメッセージを最大 PAGE_SIZE 件読み、CSV の行に変換する
+async fn load_page(
+    conn: &DatabaseConnection,
+    room_id: &room::RoomId,
+    session_id: Option<&room_session::RoomSessionId>,
+    after: Option<message::MessageId>,
+    sessions: &HashMap<uuid::Uuid, String>,
+) -> Result<Vec<(message::MessageId, LogRow)>, sea_orm::DbErr> {
+    let mut query = message::Entity::find().filter(message::Column::RoomId.eq(room_id.clone()));
+    if let Some(session_id) = session_id {
+        query = query.filter(message::Column::SessionId.eq(session_id.clone()));
+    }
+    if let Some(after) = after {
+        query = query.filter(message::Column::Id.gt(after));
+    }
+
+    let rows = query
+        .order_by_asc(message::Column::Id)
+        .limit(PAGE_SIZE)
+        .find_also_related(user::Entity)
+        .all(conn)
+        .await?;
+
+    // 教員→学生DMの宛先の名前
+    let recipient_ids = rows
+        .iter()
+        .filter_map(|(m, _)| m.recipient_id.clone())
+        .collect::<Vec<_>>();
+    let recipients = user::Entity::find()
+        .filter(user::Column::Id.is_in(recipient_ids))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|u| (u.id.0, u))
+        .collect::<HashMap<_, _>>();
+
+    let message_ids = rows.iter().map(|(m, _)| m.id.clone()).collect::<Vec<_>>();
+    let mut reactions = messages::summarize_reactions(conn, message_ids).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(m, sender)| {
+            let recipient = match (&m.recipient_id, m.is_dm) {
+                (Some(id), _) => user_label(recipients.get(&id.0), id),
+                // 学生→教員DMは教員全員宛て
+                (None, true) => "教員".to_string(),
+                (None, false) => "ALL".to_string(),
+            };
+            let row = LogRow {
+                sent_at: m.sent_at.to_rfc3339(),
+                session_started_at: sessions.get(&m.session_id.0).cloned().unwrap_or_default(),
+                sender: user_label(sender.as_ref(), &m.sender_id),
+                recipient,
+                content: m.content,
+                message_id: m.id.0.to_string(),
+                parent_message_id: m
+                    .parent_message_id
+                    .map(|id| id.0.to_string())
+                    .unwrap_or_default(),
+                stamps: format_stamps(&reactions.remove(&m.id.0).unwrap_or_default()),
+            };
+            (m.id, row)
+        })
+        .collect())
+}
+
+/// 表示名があれば表示名、なければユーザーID
+fn user_label(user: Option<&user::Model>, id: &user::UserId) -> String {
+    user.and_then(|u| u.display_name.clone())
+        .unwrap_or_else(|| id.0.to_string())
+}
+
+/// `👍×3 正解×1` の形式にする (カタログのスタンプはラベルで表す)
+fn format_stamps(reactions: &[ReactionSummary]) -> String {
+    reactions
+        .iter()
+        .map(|r| {
+            let name = SYSTEM_STAMPS
+                .iter()
+                .find(|s| s.id == r.emoji)
+                .map_or(r.emoji.as_str(), |s| s.label);
+            format!("{}×{}", name, r.count)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 行を CSV にする。`with_header` なら先頭に BOM とヘッダー行を付ける
+/// 引用符・カンマ・改行のエスケープは csv クレートに任せる
+fn encode(rows: &[LogRow], with_header: bool) -> Vec<u8> {
+    let mut buf = Vec::new();
+    if with_header {
+        buf.extend_from_slice(UTF8_BOM);
+    }
+
+    let mut writer = csv::Writer::from_writer(buf);
+    if with_header {
+        let _ = writer.write_record(HEADER);
+    }
+    for row in rows {
+        let _ = writer.write_record([
+            row.sent_at.as_str(),
+            row.session_started_at.as_str(),
+            &neutralize_formula(&row.sender),
+            &neutralize_formula(&row.recipient),
+            &neutralize_formula(&row.content),
+            row.message_id.as_str(),
+            row.parent_message_id.as_str(),
+            row.stamps.as_str(),
+        ]);
+    }
+
+    // Vec への書き込みは失敗しない
+    writer.into_inner().unwrap_or_default()
+}
+
+/// Excel で数式として解釈される先頭文字 (=, +, -, @ など) の前に ' を付ける
+/// 学生が入力した文字列がダウンロードした教員の Excel で実行されないようにする
+fn neutralize_formula(value: &str) -> String {
+    match value.chars().next() {
+        Some('=' | '+' | '-' | '@' | '\t' | '\r') => format!("'{}", value),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(content: &str) -> LogRow {
+        LogRow {
+            sent_at: "2026-10-18T10:00:00+09:00".to_string(),
+            session_started_at: "2026-10-18T09:50:00+09:00".to_string(),
+            sender: "山田 太郎".to_string(),
+            recipient: "ALL".to_string(),
+            content: content.to_string(),
+            message_id: "m1".to_string(),
+            parent_message_id: String::new(),
+            stamps: String::new(),
+        }
+    }
+
+    #[test]
+    fn header_chunk_starts_with_bom() {
+        let bytes = encode(&[], true);
+        assert!(bytes.starts_with(UTF8_BOM));
+        let text = String::from_utf8(bytes[UTF8_BOM.len()..].to_vec()).unwrap();
+        assert_eq!(text.lines().next().unwrap(), HEADER.join(","));
+        assert!(!encode(&[row("a")], false).starts_with(UTF8_BOM));
+    }
+
+    #[test]
+    fn escapes_quotes_commas_and_newlines() {
+        let bytes = encode(&[row("はい, \"そうです\"\n次の行")], false);
+        let text = String::from_utf8(bytes).unwrap();
+        assert!(text.contains("\"はい, \"\"そうです\"\"\n次の行\""));
+
+        // 読み戻すと元の文字列になる
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(text.as_bytes());
+        let record = reader.records().next().unwrap().unwrap();
+        assert_eq!(&record[4], "はい, \"そうです\"\n次の行");
+    }
+
+    #[test]
+    fn neutralizes_spreadsheet_formulas() {
+        let text = String::from_utf8(encode(&[row("=HYPERLINK(\"x\")")], false)).unwrap();
+        assert!(text.contains("'=HYPERLINK"));
+        assert_eq!(neutralize_formula("普通の文"), "普通の文");
+    }
+
+    #[test]
+    fn formats_catalog_stamps_by_label() {
+        let reactions = vec![
+            ReactionSummary {
+                emoji: "correct".to_string(),
+                count: 2,
+                user_ids: vec![],
+            },
+            ReactionSummary {
+                emoji: "🎉".to_string(),
+                count: 1,
+                user_ids: vec![],
+            },
+        ];
+        assert_eq!(format_stamps(&reactions), "正解×2 🎉×1");
+    }
+}
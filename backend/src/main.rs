@@ -1,16 +1,12 @@
 use axum::{
-    extract::{
-        ws::{WebSocket, WebSocketUpgrade},
-        Path, Query, State,
-    },
+    extract::{FromRef, State},
     http::{header, Method},
-    response::Response,
     routing::{get, post},
-    Json, Router,
+    Router,
 };
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, Database, DatabaseConnection, EntityTrait, QueryFilter, Set,
-    TransactionTrait,
+    SqlErr, TransactionTrait,
 };
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
@@ -19,28 +15,38 @@ use ts_rs::TS;
 
 mod auth;
 mod entities; // 作成したEntityモジュール
+mod error; // APIのエラー (コード付きのJSONで返す)
+mod extract; // リクエストの取り出し (失敗も AppError で返す)
+mod logs; // チャットログのCSVダウンロード
+mod members; // メンバー管理 (追加教員の指定・解除)
+mod messages; // メッセージ (履歴・スレッド) のREST API
+mod policy; // 権限ルール
+mod rooms; // 自分のルーム一覧
+mod sessions; // チャットセッション (履歴クリア)
+mod stamps; // スタンプのカタログ
+#[cfg(test)]
+mod test_support; // テスト用の共通フィクスチャ
+mod ws; // WebSocket (リアルタイムチャット)
 
-use auth::AuthUser;
+use auth::{AuthUser, FirebaseAuth};
 use entities::{prelude::*, *}; // Entityを使うためのインポート
+use error::AppError;
+use extract::{Json, Path};
 
-use futures_util::{SinkExt, StreamExt};
-use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{broadcast, Mutex};
-
-pub struct WsState {
-    pub rooms: Mutex<HashMap<room::RoomId, broadcast::Sender<String>>>,
-}
+use ws::{config::WsConfig, WsState};
 
 #[derive(Clone)]
 struct AppState {
     conn: DatabaseConnection, // Pool<Postgres> ではなく SeaORM のコネクション
     ws_state: Arc<WsState>,
+    auth: Arc<FirebaseAuth>,
 }
 
-#[derive(Deserialize)]
-pub struct WsQuery {
-    token: String,
+impl FromRef<AppState> for Arc<FirebaseAuth> {
+    fn from_ref(state: &AppState) -> Self {
+        state.auth.clone()
+    }
 }
 
 // リクエストDTO
@@ -55,6 +61,16 @@ pub struct CreateRoomRequest {
     pub slug: Option<String>,
 }
 
+// ルームの終了 / 再開
+#[derive(Deserialize, TS)]
+#[ts(
+    export,
+    export_to = "../../frontend/types/generated/set_room_active_dto.ts"
+)]
+pub struct SetRoomActiveRequest {
+    pub is_active: bool,
+}
+
 // 🌟 参加成功時にフロントエンドに返すデータ
 #[derive(Serialize, TS)]
 #[ts(
@@ -64,18 +80,8 @@ pub struct CreateRoomRequest {
 pub struct JoinRoomResponse {
     pub room: room::Model,
     pub role: entities::room_member::Role,
-}
-
-// 🌟 リアルタイムチャットでやり取りされるメッセージの型
-#[derive(Serialize, Deserialize, Clone, TS)]
-#[ts(export, export_to = "../../frontend/types/generated/ws_message.ts")]
-pub struct WsMessagePayload {
-    pub id: String, // UUIDを文字列として送る
-    pub content: String,
-    pub sender_name: String,
-    pub sender_photo_url: Option<String>,
-    pub sender_role: entities::room_member::Role,
-    pub sent_at: String,
+    // 自分のユーザーID (WebSocketのイベントが自分宛てか判定するため)
+    pub user_id: user::UserId,
 }
 
 #[tokio::main]
@@ -91,23 +97,59 @@ async fn main() {
     println!("Connection to the database is successful (SeaORM)");
 
     // WebSocket用のステートを初期化
-    let ws_state = Arc::new(WsState {
-        rooms: Mutex::new(HashMap::new()),
-    });
+    let ws_state = Arc::new(WsState::new(WsConfig::from_env()));
 
-    let state = AppState { conn, ws_state };
+    // Firebase IDトークンの検証器 (公開鍵はkidごとにキャッシュされる)
+    let auth = Arc::new(FirebaseAuth::from_env());
+
+    let state = AppState {
+        conn,
+        ws_state,
+        auth,
+    };
 
     let cors = CorsLayer::new()
-        .allow_methods([Method::GET, Method::POST])
+        .allow_methods([Method::GET, Method::POST, Method::DELETE])
         .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
         .allow_origin(Any);
 
     let app = Router::new()
         .route("/api/hello", get(hello_handler))
         .route("/api/me", get(get_me_handler))
+        .route("/api/stamps", get(stamps::list_stamps_handler))
+        .route("/api/rooms", get(rooms::list_rooms_handler))
+        .route("/api/ws/metrics", get(ws::ws_metrics_handler))
         .route("/api/room/create", post(create_room_handler))
+        .route(
+            "/api/room/{slug}",
+            axum::routing::delete(delete_room_handler),
+        )
         .route("/api/room/{slug}/join", post(join_room_handler))
-        .route("/api/room/{slug}/ws", get(ws_handler))
+        .route("/api/room/{slug}/active", post(set_room_active_handler))
+        .route("/api/room/{slug}/ws", get(ws::ws_handler))
+        .route("/api/room/{slug}/ws-ticket", post(ws::ws_ticket_handler))
+        .route("/api/room/{slug}/messages", get(messages::history_handler))
+        .route(
+            "/api/room/{slug}/messages/{message_id}/replies",
+            get(messages::thread_replies_handler),
+        )
+        .route(
+            "/api/room/{slug}/members",
+            get(members::list_members_handler),
+        )
+        .route(
+            "/api/room/{slug}/members/{user_id}/role",
+            post(members::set_role_handler),
+        )
+        .route(
+            "/api/room/{slug}/sessions",
+            get(sessions::list_sessions_handler).post(sessions::start_session_handler),
+        )
+        .route(
+            "/api/room/{slug}/sessions/{session_id}/log.csv",
+            get(logs::session_log_handler),
+        )
+        .route("/api/room/{slug}/log.csv", get(logs::room_log_handler))
         .layer(cors)
         .with_state(state);
 
@@ -135,29 +177,23 @@ async fn create_room_handler(
     State(state): State<AppState>,
     AuthUser(claims): AuthUser,
     Json(payload): Json<CreateRoomRequest>,
-) -> Result<Json<room::Model>, (axum::http::StatusCode, String)> {
+) -> Result<Json<room::Model>, AppError> {
     // 1. まずユーザーを同期 (Upsert) して UserId を取得
-    let user_id = sync_user(&state.conn, &claims)
-        .await
-        .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let user_id = sync_user(&state.conn, &claims).await?;
 
     // 2. Slug の決定とバリデーション (RoomSlugを利用)
     let slug = match payload.slug {
         Some(s) => {
             // パース失敗時は 400 Bad Request を返す
-            let valid_slug = room::RoomSlug::new(s)
-                .map_err(|e| (axum::http::StatusCode::BAD_REQUEST, e.to_string()))?;
+            let valid_slug =
+                room::RoomSlug::new(s).map_err(|e| AppError::InvalidSlug(e.to_string()))?;
             valid_slug.as_str().to_string()
         }
         None => generate_random_slug(),
     };
 
     // 3. トランザクションの開始
-    let txn = state
-        .conn
-        .begin()
-        .await
-        .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let txn = state.conn.begin().await?;
 
     // 4. Room の作成 (txn を使用)
     let new_room = room::ActiveModel {
@@ -170,10 +206,11 @@ async fn create_room_handler(
         updated_at: Set(chrono::Utc::now().into()),
     };
 
-    let inserted_room = new_room
-        .insert(&txn)
-        .await
-        .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    // slug が使われていれば 409 (一意制約で判定するので、同時に作られても片方だけが成功する)
+    let inserted_room = new_room.insert(&txn).await.map_err(|e| match e.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => AppError::SlugTaken,
+        _ => AppError::from(e),
+    })?;
 
     // 5. room_members への追加 (作成者をTEACHERとして登録)
     // ※ entities::room_members がsea-orm-cli等で生成されている前提です
@@ -184,25 +221,97 @@ async fn create_room_handler(
         joined_at: Set(chrono::Utc::now().into()),
     };
 
-    new_member
-        .insert(&txn)
-        .await
-        .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    new_member.insert(&txn).await?;
 
     // 6. トランザクションのコミット (ここで初めてDBに変更が確定する！)
-    txn.commit()
-        .await
-        .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    txn.commit().await?;
 
     Ok(Json(inserted_room))
 }
 
+/// ルーム削除ハンドラ (ルーム作成者のみ)
+/// メンバー・メッセージ・セッションは外部キーの ON DELETE CASCADE でまとめて消え、slug は再利用できるようになる
+async fn delete_room_handler(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(slug): Path<String>,
+) -> Result<axum::http::StatusCode, AppError> {
+    let user_id = sync_user(&state.conn, &claims).await?;
+
+    let (target_room, _) = find_room_membership(&state.conn, &slug, &user_id).await?;
+    if target_room.owner_id != user_id {
+        return Err(AppError::OwnerOnly("delete the room"));
+    }
+
+    room::Entity::delete_by_id(target_room.id.clone())
+        .exec(&state.conn)
+        .await?;
+
+    // 接続中のソケットに削除を知らせて切断する (招待リンクからの再接続も 404 になる)
+    ws::close_room(
+        &state.ws_state,
+        &target_room.id,
+        &ws::protocol::ServerEvent::RoomDeleted,
+        ws::routing::Disconnect {
+            code: ws::protocol::close_code::ROOM_DELETED,
+            reason: "Room deleted",
+        },
+    )
+    .await;
+
+    Ok(axum::http::StatusCode::NO_CONTENT)
+}
+
+/// ルームの終了 / 再開ハンドラ (ルーム作成者のみ)
+/// 終了したルームには新しく参加できず、接続中の学生は閲覧のみになる
+async fn set_room_active_handler(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(slug): Path<String>,
+    Json(payload): Json<SetRoomActiveRequest>,
+) -> Result<Json<room::Model>, AppError> {
+    let user_id = sync_user(&state.conn, &claims).await?;
+
+    let (target_room, _) = find_room_membership(&state.conn, &slug, &user_id).await?;
+    if target_room.owner_id != user_id {
+        return Err(AppError::OwnerOnly("end or reopen the room"));
+    }
+
+    let mut active: room::ActiveModel = target_room.into();
+    active.is_active = Set(payload.is_active);
+    active.updated_at = Set(chrono::Utc::now().into());
+    let updated = active.update(&state.conn).await?;
+
+    // 接続中の全員に知らせる (学生の画面は閲覧のみに切り替わる)
+    let event = ws::protocol::ServerEvent::RoomStatusChanged {
+        is_active: updated.is_active,
+    };
+    ws::broadcast(
+        &state.ws_state,
+        &updated.id,
+        ws::routing::Audience::Everyone,
+        None,
+        &event,
+    )
+    .await;
+
+    Ok(Json(updated))
+}
+
 /// ユーザー情報を同期する (SeaORM版)
 /// 戻り値が厳格な `user::UserId` になっていることに注目！
 async fn sync_user(
     conn: &DatabaseConnection,
     claims: &auth::Claims,
 ) -> Result<user::UserId, sea_orm::DbErr> {
+    Ok(upsert_user(conn, claims).await?.id)
+}
+
+/// ユーザー情報を同期し、最新の `user::Model` を返す
+async fn upsert_user(
+    conn: &DatabaseConnection,
+    claims: &auth::Claims,
+) -> Result<user::Model, sea_orm::DbErr> {
     // 1. 既存ユーザーを検索
     let existing_user = User::find()
         .filter(user::Column::FirebaseUid.eq(&claims.sub))
@@ -217,8 +326,7 @@ async fn sync_user(
         active.photo_url = Set(claims.picture.clone());
         active.updated_at = Set(chrono::Utc::now().into());
 
-        let updated = active.update(conn).await?;
-        Ok(updated.id)
+        active.update(conn).await
     } else {
         // 3. 新規作成 (Insert)
         let new_user = user::ActiveModel {
@@ -231,11 +339,37 @@ async fn sync_user(
             updated_at: Set(chrono::Utc::now().into()),
         };
 
-        let inserted = new_user.insert(conn).await?;
-        Ok(inserted.id)
+        new_user.insert(conn).await
     }
 }
 
+/// slug からルームを引き、ユーザーがそのメンバーであることを確認する
+/// ルームがなければ 404、メンバーでなければ 403
+/// 返すメンバーの role は現在のセッションでの実効権限 (追加教員を含む)
+async fn find_room_membership(
+    conn: &DatabaseConnection,
+    slug: &str,
+    user_id: &user::UserId,
+) -> Result<(room::Model, room_member::Model), AppError> {
+    let target_room = room::Entity::find()
+        .filter(room::Column::Slug.eq(slug))
+        .one(conn)
+        .await?
+        .ok_or(AppError::RoomNotFound)?;
+
+    let mut member = room_member::Entity::find()
+        .filter(room_member::Column::RoomId.eq(target_room.id.clone()))
+        .filter(room_member::Column::UserId.eq(user_id.clone()))
+        .one(conn)
+        .await?
+        .ok_or(AppError::NotAMember)?;
+
+    // 追加教員の指定はセッションごとなので、現在のセッションでの権限に置き換える
+    member.role = sessions::effective_role(conn, &target_room, user_id).await?;
+
+    Ok((target_room, member))
+}
+
 /// 8文字のランダムなSlugを生成するヘルパー
 fn generate_random_slug() -> String {
     use rand::{distributions::Alphanumeric, Rng};
@@ -251,29 +385,21 @@ fn generate_random_slug() -> String {
 async fn join_room_handler(
     State(state): State<AppState>,
     AuthUser(claims): AuthUser,
-    axum::extract::Path(slug): axum::extract::Path<String>,
-) -> Result<Json<JoinRoomResponse>, (axum::http::StatusCode, String)> {
+    Path(slug): Path<String>,
+) -> Result<Json<JoinRoomResponse>, AppError> {
     // 1. ユーザーを同期して UserId を取得
-    let user_id = sync_user(&state.conn, &claims)
-        .await
-        .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let user_id = sync_user(&state.conn, &claims).await?;
 
     // 2. 指定された slug の部屋が存在するか確認
     let target_room = room::Entity::find()
         .filter(room::Column::Slug.eq(slug))
         .one(&state.conn)
-        .await
-        .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .await?;
 
     // 部屋がなければ 404
     let target_room = match target_room {
         Some(r) => r,
-        None => {
-            return Err((
-                axum::http::StatusCode::NOT_FOUND,
-                "Room not found".to_string(),
-            ))
-        }
+        None => return Err(AppError::RoomNotFound),
     };
 
     // 3. 既にメンバーとして登録されているか確認
@@ -281,26 +407,27 @@ async fn join_room_handler(
         .filter(entities::room_member::Column::RoomId.eq(target_room.id.clone()))
         .filter(entities::room_member::Column::UserId.eq(user_id.clone()))
         .one(&state.conn)
-        .await
-        .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .await?;
 
     // 4. メンバー登録処理と権限の決定
-    let role = if let Some(member) = existing_member {
-        // 既にメンバーならその権限を返す
-        member.role
+    let role = if existing_member.is_some() {
+        // 既にメンバーなら現在のセッションでの権限を返す
+        sessions::effective_role(&state.conn, &target_room, &user_id).await?
     } else {
+        // 終了したルームには新しく参加できない
+        if !target_room.is_active {
+            return Err(AppError::RoomEnded);
+        }
+
         // 初めての参加なら STUDENT として登録
         let new_member = entities::room_member::ActiveModel {
             room_id: Set(target_room.id.clone()),
-            user_id: Set(user_id),
+            user_id: Set(user_id.clone()),
             role: Set(entities::room_member::Role::Student),
             joined_at: Set(chrono::Utc::now().into()),
         };
 
-        new_member
-            .insert(&state.conn)
-            .await
-            .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        new_member.insert(&state.conn).await?;
 
         entities::room_member::Role::Student
     };
@@ -309,160 +436,23 @@ async fn join_room_handler(
     Ok(Json(JoinRoomResponse {
         room: target_room,
         role,
+        user_id,
     }))
 }
 
-async fn ws_handler(
-    ws: WebSocketUpgrade,
-    Path(slug): Path<String>,
-    Query(query): Query<WsQuery>,
-    State(state): State<AppState>,
-) -> Result<Response, (axum::http::StatusCode, String)> {
-    // 1. クエリパラメータのトークンを検証
-    let claims = auth::verify_token(&query.token)?;
-
-    // 2. ユーザーを同期して UserId を取得 (のちほどメッセージ送信者を特定するため)
-    let user_id = sync_user(&state.conn, &claims)
-        .await
-        .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    // 3. (オプション) 該当の部屋が存在するか、ユーザーが参加メンバーか確認する処理を入れるとより安全です
-    // 今回は一旦スキップし、接続を許可します
-
-    // 4. WebSocketのコネクションにアップグレード
-    // アップグレードが成功したら `handle_socket` という非同期タスクに処理を移譲します
-    Ok(ws.on_upgrade(move |socket| handle_socket(socket, state, slug, user_id)))
-}
-
-async fn handle_socket(
-    socket: WebSocket,
-    state: AppState,
-    slug: String,
-    user_id: entities::user::UserId,
-) {
-    let (mut ws_sender, mut ws_receiver) = socket.split();
-
-    // 1. ルーム情報の取得
-    let target_room = match room::Entity::find()
-        .filter(room::Column::Slug.eq(&slug))
-        .one(&state.conn)
-        .await
-    {
-        Ok(Some(r)) => r,
-        _ => return,
-    };
-    let room_id = target_room.id;
-
-    // 🌟 2. 接続してきたユーザーの情報と権限をDBから取得しておく！
-    let current_user = user::Entity::find_by_id(user_id.clone())
-        .one(&state.conn)
-        .await
-        .unwrap()
-        .unwrap(); // 実際の運用ではエラーハンドリング推奨
-
-    let current_member = entities::room_member::Entity::find()
-        .filter(entities::room_member::Column::RoomId.eq(room_id.clone()))
-        .filter(entities::room_member::Column::UserId.eq(user_id.clone()))
-        .one(&state.conn)
-        .await
-        .unwrap()
-        .unwrap();
-
-    let rx = {
-        let mut rooms = state.ws_state.rooms.lock().await;
-        let tx = rooms.entry(room_id.clone()).or_insert_with(|| {
-            let (tx, _rx) = broadcast::channel(100);
-            tx
-        });
-        tx.subscribe()
-    };
-
-    // 送信タスク
-    let mut send_task = tokio::spawn(async move {
-        let mut rx = rx;
-        while let Ok(msg) = rx.recv().await {
-            if ws_sender
-                .send(axum::extract::ws::Message::Text(msg.into()))
-                .await
-                .is_err()
-            {
-                break;
-            }
-        }
-    });
-
-    // 受信タスク
-    let state_clone = state.clone();
-    let room_id_clone = room_id.clone();
-    let user_id_clone = user_id.clone();
-
-    // クロージャに値をMoveさせるためのクローン
-    let sender_name = current_user
-        .display_name
-        .unwrap_or_else(|| "名無し".to_string());
-    let sender_photo_url = current_user.photo_url;
-    let sender_role = current_member.role;
-
-    let mut recv_task = tokio::spawn(async move {
-        while let Some(Ok(msg)) = ws_receiver.next().await {
-            if let axum::extract::ws::Message::Text(text) = msg {
-                let text_str = text.to_string();
-                let message_id = uuid::Uuid::now_v7();
-
-                // DBに保存
-                let new_message = entities::message::ActiveModel {
-                    id: Set(entities::message::MessageId(message_id.clone())),
-                    room_id: Set(room_id_clone.clone()),
-                    sender_id: Set(user_id_clone.clone()),
-                    content: Set(text_str.clone()),
-                    is_dm: Set(false),
-                    sent_at: Set(chrono::Utc::now().into()),
-                    ..Default::default()
-                };
-
-                if let Err(e) = new_message.insert(&state_clone.conn).await {
-                    eprintln!("Failed to save message to DB: {}", e);
-                    continue;
-                }
-
-                // フロントエンドに送るJSONペイロードを作成
-                let payload = WsMessagePayload {
-                    id: message_id.to_string(),
-                    content: text_str,
-                    sender_name: sender_name.clone(),
-                    sender_photo_url: sender_photo_url.clone(),
-                    sender_role: sender_role.clone(),
-                    sent_at: chrono::Utc::now().to_rfc3339(),
-                };
-
-                // JSON文字列に変換
-                if let Ok(json_string) = serde_json::to_string(&payload) {
-                    // ルームの全員にJSONを配信
-                    let rooms = state_clone.ws_state.rooms.lock().await;
-                    if let Some(tx) = rooms.get(&room_id_clone) {
-                        let _ = tx.send(json_string);
-                    }
-                }
-            }
-        }
-    });
-
-    tokio::select! {
-        _ = (&mut send_task) => recv_task.abort(),
-        _ = (&mut recv_task) => send_task.abort(),
-    };
-
-    println!("👋 User {:?} disconnected from room: {}", user_id, slug);
-}
-
 /// TS型定義エクスポート
 #[cfg(test)]
 mod tests {
     use super::*; // main.rs内の CreateRoomRequest などを読み込む
     use crate::entities::message::{MessageId, Model as Message};
+    use crate::entities::reaction::{Model as Reaction, ReactionId};
     use crate::entities::room::{Model as Room, RoomId};
     use crate::entities::room_member::{Model as RoomMember, Role};
+    use crate::entities::room_session::{Model as RoomSession, RoomSessionId};
     use crate::entities::user::{Model as User, UserId};
+    use crate::ws::protocol::{
+        ClientEvent, ReactionSummary, ServerEvent, WsErrorCode, WsMessagePayload,
+    };
     use ts_rs::TS;
 
     #[test]
@@ -475,6 +465,8 @@ mod tests {
         Room::export().expect("Failed to export Room");
         RoomMember::export().expect("Failed to export RoomMember");
         Message::export().expect("Failed to export RoomMember");
+        Reaction::export().expect("Failed to export Reaction");
+        RoomSession::export().expect("Failed to export RoomSession");
 
         Role::export().expect("Failed to export Role");
 
@@ -482,11 +474,31 @@ mod tests {
         UserId::export().expect("Failed to export UserId");
         RoomId::export().expect("Failed to export RoomId");
         MessageId::export().expect("Failed to export MessageId");
+        ReactionId::export().expect("Failed to export ReactionId");
+        RoomSessionId::export().expect("Failed to export RoomSessionId");
 
         // 3. APIのリクエストDTOをエクスポート
         CreateRoomRequest::export().expect("Failed to export CreateRoomRequest");
         JoinRoomResponse::export().expect("Failed to export JoinRoomResponse");
+        members::MemberInfo::export().expect("Failed to export MemberInfo");
+        members::SetRoleRequest::export().expect("Failed to export SetRoleRequest");
+        SetRoomActiveRequest::export().expect("Failed to export SetRoomActiveRequest");
+        rooms::RoomScope::export().expect("Failed to export RoomScope");
+        rooms::RoomSummary::export().expect("Failed to export RoomSummary");
+        rooms::RoomList::export().expect("Failed to export RoomList");
+        ws::WsTicketResponse::export().expect("Failed to export WsTicketResponse");
+        error::ErrorCode::export().expect("Failed to export ErrorCode");
+        error::ErrorResponse::export().expect("Failed to export ErrorResponse");
         WsMessagePayload::export().expect("Failed to export WsMessagePayload");
+        ReactionSummary::export().expect("Failed to export ReactionSummary");
+
+        stamps::Stamp::export().expect("Failed to export Stamp");
+        messages::MessageHistory::export().expect("Failed to export MessageHistory");
+
+        // 4. WebSocketのイベントをエクスポート
+        ClientEvent::export().expect("Failed to export ClientEvent");
+        ServerEvent::export().expect("Failed to export ServerEvent");
+        WsErrorCode::export().expect("Failed to export WsErrorCode");
 
         println!("✨ TypeScript bindings updated securely!");
     }
@@ -0,0 +1,204 @@
+//! ルームのメンバー一覧と管理 (追加教員の指定・解除)
+
+use axum::extract::State;
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, sea_query::OnConflict, ColumnTrait, EntityTrait, QueryFilter,
+    QueryOrder, Set,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use ts_rs::TS;
+
+use crate::auth::AuthUser;
+use crate::entities::{room_member, session_teacher, user};
+use crate::error::AppError;
+use crate::extract::{Json, Path};
+use crate::ws::{
+    self,
+    protocol::ServerEvent,
+    routing::{Audience, RoleChange},
+};
+use crate::{find_room_membership, sync_user, AppState};
+use crate::{policy, sessions};
+
+/// メンバー一覧の1人分
+#[derive(Serialize, Clone, Debug, TS)]
+#[ts(export, export_to = "../../frontend/types/generated/member_info.ts")]
+pub struct MemberInfo {
+    pub user_id: user::UserId,
+    pub display_name: Option<String>,
+    pub photo_url: Option<String>,
+    /// 現在のセッションでの権限
+    pub role: room_member::Role,
+    pub joined_at: DateTimeWithTimeZone,
+    /// WebSocket で接続中か
+    pub online: bool,
+}
+
+impl MemberInfo {
+    pub fn new(
+        user: &user::Model,
+        member: &room_member::Model,
+        role: room_member::Role,
+        online: bool,
+    ) -> Self {
+        Self {
+            user_id: user.id.clone(),
+            display_name: user.display_name.clone(),
+            photo_url: user.photo_url.clone(),
+            role,
+            joined_at: member.joined_at,
+            online,
+        }
+    }
+}
+
+/// ルームのメンバー一覧を参加順に返すハンドラ (教員がDMの宛先を選ぶのに使う)
+pub async fn list_members_handler(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(slug): Path<String>,
+) -> Result<Json<Vec<MemberInfo>>, AppError> {
+    let user_id = sync_user(&state.conn, &claims).await?;
+
+    let (target_room, _) = find_room_membership(&state.conn, &slug, &user_id).await?;
+
+    let rows = room_member::Entity::find()
+        .filter(room_member::Column::RoomId.eq(target_room.id.clone()))
+        .order_by_asc(room_member::Column::JoinedAt)
+        .find_also_related(user::Entity)
+        .all(&state.conn)
+        .await?;
+
+    // 進行中のセッションで教員に指定されているメンバー
+    let active = sessions::find_active(&state.conn, &target_room.id).await?;
+    let granted = match active {
+        Some(session) => session_teacher::Entity::find()
+            .filter(session_teacher::Column::SessionId.eq(session.id))
+            .all(&state.conn)
+            .await?
+            .into_iter()
+            .map(|g| g.user_id.0)
+            .collect::<HashSet<_>>(),
+        None => HashSet::new(),
+    };
+
+    let online = ws::online_users(&state.ws_state, &target_room.id).await;
+
+    let members = rows
+        .into_iter()
+        .filter_map(|(member, user)| {
+            let user = user?;
+            let role = match policy::permanent_role(&target_room, &user.id) {
+                room_member::Role::Student if granted.contains(&user.id.0) => {
+                    room_member::Role::Teacher
+                }
+                role => role,
+            };
+            let is_online = online.contains(&user.id);
+            Some(MemberInfo::new(&user, &member, role, is_online))
+        })
+        .collect();
+
+    Ok(Json(members))
+}
+
+#[derive(Deserialize, TS)]
+#[ts(
+    export,
+    export_to = "../../frontend/types/generated/set_role_request.ts"
+)]
+pub struct SetRoleRequest {
+    pub role: room_member::Role,
+}
+
+/// メンバーを教員に設定 / 解除するハンドラ (ルーム作成者のみ)
+/// 指定は進行中のセッションの間だけ有効。接続中のソケットにはすぐに反映する
+pub async fn set_role_handler(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path((slug, target_user_id)): Path<(String, user::UserId)>,
+    Json(payload): Json<SetRoleRequest>,
+) -> Result<Json<MemberInfo>, AppError> {
+    let user_id = sync_user(&state.conn, &claims).await?;
+
+    // 1. 操作できるのはルーム作成者だけ
+    let (target_room, _) = find_room_membership(&state.conn, &slug, &user_id).await?;
+    if target_room.owner_id != user_id {
+        return Err(AppError::OwnerOnly("change roles"));
+    }
+
+    // 2. 作成者自身の教員権限は外せない
+    if target_user_id == target_room.owner_id && payload.role == room_member::Role::Student {
+        return Err(AppError::CannotDemoteOwner);
+    }
+
+    let (member, target_user) = room_member::Entity::find()
+        .filter(room_member::Column::RoomId.eq(target_room.id.clone()))
+        .filter(room_member::Column::UserId.eq(target_user_id.clone()))
+        .find_also_related(user::Entity)
+        .one(&state.conn)
+        .await?
+        .and_then(|(member, user)| Some((member, user?)))
+        .ok_or(AppError::MemberNotFound)?;
+
+    // 3. 進行中のセッションでの指定として保存する (作成者は常に教員なので何もしない)
+    if target_user_id != target_room.owner_id {
+        let session = sessions::current_or_start(&state.conn, &target_room.id).await?;
+
+        match payload.role {
+            room_member::Role::Teacher => {
+                session_teacher::Entity::insert(session_teacher::ActiveModel {
+                    session_id: Set(session.id),
+                    user_id: Set(target_user_id.clone()),
+                    granted_at: Set(chrono::Utc::now().into()),
+                })
+                .on_conflict(
+                    OnConflict::columns([
+                        session_teacher::Column::SessionId,
+                        session_teacher::Column::UserId,
+                    ])
+                    .do_nothing()
+                    .to_owned(),
+                )
+                .exec_without_returning(&state.conn)
+                .await?;
+            }
+            room_member::Role::Student => {
+                session_teacher::Entity::delete_many()
+                    .filter(session_teacher::Column::SessionId.eq(session.id))
+                    .filter(session_teacher::Column::UserId.eq(target_user_id.clone()))
+                    .exec(&state.conn)
+                    .await?;
+            }
+        }
+    }
+
+    // 4. 接続中の全員に知らせる (本人の接続はここで DM の権限が切り替わる)
+    let event = ServerEvent::RoleChanged {
+        user_id: target_user_id.clone(),
+        role: payload.role.clone(),
+    };
+    ws::broadcast(
+        &state.ws_state,
+        &target_room.id,
+        Audience::Everyone,
+        Some(RoleChange::Set {
+            user_id: target_user_id.clone(),
+            role: payload.role.clone(),
+        }),
+        &event,
+    )
+    .await;
+
+    // 5. 変更後の実効権限で返す (room_members の role は恒久的な権限のまま)
+    let online = ws::online_users(&state.ws_state, &target_room.id)
+        .await
+        .contains(&target_user_id);
+    Ok(Json(MemberInfo::new(
+        &target_user,
+        &member,
+        payload.role,
+        online,
+    )))
+}
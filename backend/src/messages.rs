@@ -0,0 +1,294 @@
+use axum::extract::State;
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use ts_rs::TS;
+
+use crate::auth::AuthUser;
+use crate::entities::{message, reaction, room, room_member, room_session, session_teacher, user};
+use crate::error::AppError;
+use crate::extract::{Json, Path, Query};
+use crate::ws::protocol::{ReactionSummary, WsMessagePayload};
+use crate::{find_room_membership, sync_user, AppState};
+use crate::{policy, sessions};
+
+/// 1ページの既定件数と上限
+const DEFAULT_HISTORY_LIMIT: u64 = 50;
+const MAX_HISTORY_LIMIT: u64 = 100;
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    /// このメッセージより前を取得する (省略時は最新から)
+    before: Option<message::MessageId>,
+    limit: Option<u64>,
+    /// 過去のセッションを見る場合に指定する (教員のみ。省略時は進行中のセッション)
+    session_id: Option<room_session::RoomSessionId>,
+}
+
+/// 履歴の1ページ (古い順)
+#[derive(Serialize, Clone, Debug, TS)]
+#[ts(
+    export,
+    export_to = "../../frontend/types/generated/message_history.ts"
+)]
+pub struct MessageHistory {
+    pub messages: Vec<WsMessagePayload>,
+    /// さらに古いメッセージがあるか
+    pub has_more: bool,
+}
+
+/// メッセージ履歴を新しい方からページングして返すハンドラ
+/// `before` に前ページの先頭のIDを渡すと、それより古いメッセージを返す
+/// 学生は進行中のセッションのメッセージだけを閲覧できる
+pub async fn history_handler(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(slug): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<MessageHistory>, AppError> {
+    let user_id = sync_user(&state.conn, &claims).await?;
+
+    let (target_room, member) = find_room_membership(&state.conn, &slug, &user_id).await?;
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .clamp(1, MAX_HISTORY_LIMIT);
+
+    let active = sessions::find_active(&state.conn, &target_room.id).await?;
+
+    let session_id = match (query.session_id, &member.role) {
+        (Some(id), room_member::Role::Teacher) => {
+            // 別のルームのセッションは存在しないものとして扱う
+            room_session::Entity::find_by_id(id)
+                .filter(room_session::Column::RoomId.eq(target_room.id.clone()))
+                .one(&state.conn)
+                .await?
+                .ok_or(AppError::SessionNotFound)?
+                .id
+        }
+        (Some(id), room_member::Role::Student) if Some(&id) != active.as_ref().map(|s| &s.id) => {
+            return Err(AppError::TeacherOnly("view past sessions"));
+        }
+        _ => match active {
+            Some(active) => active.id,
+            // まだセッションが始まっていなければ履歴は空
+            None => {
+                return Ok(Json(MessageHistory {
+                    messages: Vec::new(),
+                    has_more: false,
+                }))
+            }
+        },
+    };
+
+    let history = fetch_history(
+        &state.conn,
+        &target_room.id,
+        &session_id,
+        &member,
+        query.before,
+        limit,
+    )
+    .await?;
+
+    Ok(Json(history))
+}
+
+/// セッション内で閲覧者が見てよいメッセージだけを、`before` より前から最大 `limit` 件取得する
+/// id をキーセットページングのキーに使う
+pub async fn fetch_history(
+    conn: &DatabaseConnection,
+    room_id: &room::RoomId,
+    session_id: &room_session::RoomSessionId,
+    viewer: &room_member::Model,
+    before: Option<message::MessageId>,
+    limit: u64,
+) -> Result<MessageHistory, sea_orm::DbErr> {
+    let mut query = message::Entity::find()
+        .filter(message::Column::RoomId.eq(room_id.clone()))
+        .filter(message::Column::SessionId.eq(session_id.clone()))
+        .filter(visible_to(viewer));
+
+    if let Some(before) = before {
+        query = query.filter(message::Column::Id.lt(before));
+    }
+
+    // 1件多く取って、さらに古いメッセージがあるか判定する
+    let mut rows = query
+        .order_by_desc(message::Column::Id)
+        .limit(limit + 1)
+        .find_also_related(user::Entity)
+        .all(conn)
+        .await?;
+
+    let has_more = rows.len() as u64 > limit;
+    rows.truncate(limit as usize);
+    rows.reverse();
+
+    let messages = to_payloads(conn, room_id, rows).await?;
+
+    Ok(MessageHistory { messages, has_more })
+}
+
+/// セッション内で `after` より後に届いた、閲覧者が見てよいメッセージを古い順にすべて取得する
+/// `limit` 件を超える場合は None (再接続までの空白が長すぎるので履歴を取り直してもらう)
+pub async fn fetch_after(
+    conn: &DatabaseConnection,
+    room_id: &room::RoomId,
+    session_id: &room_session::RoomSessionId,
+    viewer: &room_member::Model,
+    after: &message::MessageId,
+    limit: u64,
+) -> Result<Option<Vec<WsMessagePayload>>, sea_orm::DbErr> {
+    let rows = message::Entity::find()
+        .filter(message::Column::RoomId.eq(room_id.clone()))
+        .filter(message::Column::SessionId.eq(session_id.clone()))
+        .filter(visible_to(viewer))
+        .filter(message::Column::Id.gt(after.clone()))
+        .order_by_asc(message::Column::Id)
+        .limit(limit + 1)
+        .find_also_related(user::Entity)
+        .all(conn)
+        .await?;
+
+    if rows.len() as u64 > limit {
+        return Ok(None);
+    }
+
+    Ok(Some(to_payloads(conn, room_id, rows).await?))
+}
+
+/// 閲覧者が見てよいメッセージの条件 (`policy::can_view` をSQLにしたもの)
+/// 教員はすべて、学生は全体チャットと自分が当事者のDMだけ
+fn visible_to(viewer: &room_member::Model) -> Condition {
+    match viewer.role {
+        room_member::Role::Teacher => Condition::all(),
+        room_member::Role::Student => Condition::any()
+            .add(message::Column::IsDm.eq(false))
+            .add(message::Column::SenderId.eq(viewer.user_id.clone()))
+            .add(message::Column::RecipientId.eq(viewer.user_id.clone())),
+    }
+}
+
+/// DMスレッドの返信一覧を時系列で返すハンドラ
+pub async fn thread_replies_handler(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path((slug, message_id)): Path<(String, message::MessageId)>,
+) -> Result<Json<Vec<WsMessagePayload>>, AppError> {
+    let user_id = sync_user(&state.conn, &claims).await?;
+
+    let (target_room, member) = find_room_membership(&state.conn, &slug, &user_id).await?;
+
+    let active = sessions::find_active(&state.conn, &target_room.id).await?;
+
+    // 1. 元メッセージを取得 (見えないDM・過去のセッションは存在しないものとして扱う)
+    let root = message::Entity::find_by_id(message_id.clone())
+        .filter(message::Column::RoomId.eq(target_room.id.clone()))
+        .one(&state.conn)
+        .await?
+        .filter(|m| policy::can_view(&user_id, &member.role, m))
+        .filter(|m| policy::can_view_session(&member.role, m, active.as_ref().map(|s| &s.id)))
+        .ok_or(AppError::MessageNotFound)?;
+
+    policy::check_reply_parent(&root).map_err(|v| AppError::InvalidReplyParent(v.to_string()))?;
+
+    // 2. 返信を古い順 (id 順) に取得
+    let replies = message::Entity::find()
+        .filter(message::Column::ParentMessageId.eq(root.id))
+        .order_by_asc(message::Column::Id)
+        .find_also_related(user::Entity)
+        .all(&state.conn)
+        .await?;
+
+    let payloads = to_payloads(&state.conn, &target_room.id, replies).await?;
+
+    Ok(Json(payloads))
+}
+
+/// メッセージと送信者の組をペイロードに変換する
+/// 送信者の権限はルームのメンバー情報と、そのメッセージのセッションでの追加教員の指定から決める
+/// スタンプは絵文字ごとに集計して付ける
+pub async fn to_payloads(
+    conn: &DatabaseConnection,
+    room_id: &room::RoomId,
+    rows: Vec<(message::Model, Option<user::Model>)>,
+) -> Result<Vec<WsMessagePayload>, sea_orm::DbErr> {
+    let sender_ids = rows
+        .iter()
+        .map(|(m, _)| m.sender_id.clone())
+        .collect::<Vec<_>>();
+
+    let roles = room_member::Entity::find()
+        .filter(room_member::Column::RoomId.eq(room_id.clone()))
+        .filter(room_member::Column::UserId.is_in(sender_ids))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|m| (m.user_id.0, m.role))
+        .collect::<HashMap<_, _>>();
+
+    let session_ids = rows
+        .iter()
+        .map(|(m, _)| m.session_id.clone())
+        .collect::<HashSet<_>>();
+    let grants = session_teacher::Entity::find()
+        .filter(session_teacher::Column::SessionId.is_in(session_ids))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|g| (g.session_id.0, g.user_id.0))
+        .collect::<HashSet<_>>();
+
+    let message_ids = rows.iter().map(|(m, _)| m.id.clone()).collect::<Vec<_>>();
+    let mut reactions = summarize_reactions(conn, message_ids).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(message, sender)| {
+            let granted = grants.contains(&(message.session_id.0, message.sender_id.0));
+            let role = match roles.get(&message.sender_id.0) {
+                Some(room_member::Role::Teacher) => room_member::Role::Teacher,
+                _ if granted => room_member::Role::Teacher,
+                _ => room_member::Role::Student,
+            };
+            let summaries = reactions.remove(&message.id.0).unwrap_or_default();
+            let mut payload = WsMessagePayload::new(message, sender.as_ref(), role);
+            payload.reactions = summaries;
+            payload
+        })
+        .collect())
+}
+
+/// メッセージごとのスタンプを、最初に付けられた順で絵文字ごとにまとめる
+pub async fn summarize_reactions(
+    conn: &DatabaseConnection,
+    message_ids: Vec<message::MessageId>,
+) -> Result<HashMap<uuid::Uuid, Vec<ReactionSummary>>, sea_orm::DbErr> {
+    let rows = reaction::Entity::find()
+        .filter(reaction::Column::MessageId.is_in(message_ids))
+        .order_by_asc(reaction::Column::CreatedAt)
+        .all(conn)
+        .await?;
+
+    let mut summaries: HashMap<uuid::Uuid, Vec<ReactionSummary>> = HashMap::new();
+    for row in rows {
+        let list = summaries.entry(row.message_id.0).or_default();
+        match list.iter_mut().find(|s| s.emoji == row.emoji) {
+            Some(summary) => {
+                summary.count += 1;
+                summary.user_ids.push(row.user_id);
+            }
+            None => list.push(ReactionSummary {
+                emoji: row.emoji,
+                count: 1,
+                user_ids: vec![row.user_id],
+            }),
+        }
+    }
+
+    Ok(summaries)
+}
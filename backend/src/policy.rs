@@ -0,0 +1,237 @@
+//! ルーム内の権限ルール (Axum や DB には依存しない)
+
+use crate::entities::message;
+use crate::entities::room;
+use crate::entities::room_member::Role;
+use crate::entities::room_session::RoomSessionId;
+use crate::entities::user::UserId;
+
+/// DMの届け先
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DmRoute {
+    /// 学生→教員DM: 教員全員に届く
+    ToTeachers,
+    /// 教員→学生DM: 指定した学生と教員全員に届く
+    ToStudent,
+}
+
+/// 仕様で禁止されているDM
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DmViolation {
+    /// 学生同士のDMは不可
+    StudentToStudent,
+    /// 教員同士のDMは不可
+    TeacherToTeacher,
+    /// 教員からのDMは宛先の学生を指定する必要がある
+    RecipientRequired,
+}
+
+impl std::fmt::Display for DmViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            DmViolation::StudentToStudent => "Students cannot send DMs to other students",
+            DmViolation::TeacherToTeacher => "Teachers cannot send DMs to other teachers",
+            DmViolation::RecipientRequired => "Recipient is required",
+        };
+        f.write_str(message)
+    }
+}
+
+/// セッションをまたいで変わらない権限
+/// ルーム作成者だけが常に教員で、追加教員はセッションごとの指定で教員になる
+pub fn permanent_role(room: &room::Model, user_id: &UserId) -> Role {
+    if room.owner_id == *user_id {
+        Role::Teacher
+    } else {
+        Role::Student
+    }
+}
+
+/// 送信者と宛先の権限から、DMを送ってよいか・どこに届けるかを決める
+/// `recipient` は宛先の指定がなければ None
+pub fn route_dm(sender: &Role, recipient: Option<&Role>) -> Result<DmRoute, DmViolation> {
+    match (sender, recipient) {
+        // 学生は宛先を省略するか教員を指定する (どちらも教員全員に届く)
+        (Role::Student, None) | (Role::Student, Some(Role::Teacher)) => Ok(DmRoute::ToTeachers),
+        (Role::Student, Some(Role::Student)) => Err(DmViolation::StudentToStudent),
+        (Role::Teacher, Some(Role::Student)) => Ok(DmRoute::ToStudent),
+        (Role::Teacher, Some(Role::Teacher)) => Err(DmViolation::TeacherToTeacher),
+        (Role::Teacher, None) => Err(DmViolation::RecipientRequired),
+    }
+}
+
+/// メッセージを閲覧できるか
+/// 全体チャットは全員、DMは教員全員と当事者の学生だけが見られる
+pub fn can_view(viewer: &UserId, role: &Role, message: &message::Model) -> bool {
+    !message.is_dm
+        || *role == Role::Teacher
+        || message.sender_id == *viewer
+        || message.recipient_id.as_ref() == Some(viewer)
+}
+
+/// メッセージのセッションを閲覧できるか
+/// 学生は進行中のセッションだけ、教員は過去のセッション (ログ) も見られる
+pub fn can_view_session(
+    role: &Role,
+    message: &message::Model,
+    active: Option<&RoomSessionId>,
+) -> bool {
+    *role == Role::Teacher || active == Some(&message.session_id)
+}
+
+/// DMスレッドの当事者の学生
+/// 学生→教員DMは送信者、教員→学生DMは宛先が学生になる
+pub fn thread_student(root: &message::Model) -> &UserId {
+    root.recipient_id.as_ref().unwrap_or(&root.sender_id)
+}
+
+/// 返信できないメッセージ
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplyViolation {
+    /// スレッドはDMでのみ使える
+    NotADm,
+    /// スレッドの深さは1階層のみ
+    NestedReply,
+}
+
+impl std::fmt::Display for ReplyViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            ReplyViolation::NotADm => "Threads are only available on DMs",
+            ReplyViolation::NestedReply => "Cannot reply to a reply",
+        };
+        f.write_str(message)
+    }
+}
+
+/// 返信先として使えるメッセージか (DMの元メッセージのみ)
+pub fn check_reply_parent(parent: &message::Model) -> Result<(), ReplyViolation> {
+    if !parent.is_dm {
+        return Err(ReplyViolation::NotADm);
+    }
+    if parent.parent_message_id.is_some() {
+        return Err(ReplyViolation::NestedReply);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{message, room_owned_by, user};
+
+    #[test]
+    fn student_dm_goes_to_teachers() {
+        assert_eq!(route_dm(&Role::Student, None), Ok(DmRoute::ToTeachers));
+        assert_eq!(
+            route_dm(&Role::Student, Some(&Role::Teacher)),
+            Ok(DmRoute::ToTeachers)
+        );
+    }
+
+    #[test]
+    fn teacher_dm_goes_to_a_student() {
+        assert_eq!(
+            route_dm(&Role::Teacher, Some(&Role::Student)),
+            Ok(DmRoute::ToStudent)
+        );
+    }
+
+    #[test]
+    fn rejects_student_to_student() {
+        assert_eq!(
+            route_dm(&Role::Student, Some(&Role::Student)),
+            Err(DmViolation::StudentToStudent)
+        );
+    }
+
+    #[test]
+    fn rejects_teacher_to_teacher() {
+        assert_eq!(
+            route_dm(&Role::Teacher, Some(&Role::Teacher)),
+            Err(DmViolation::TeacherToTeacher)
+        );
+    }
+
+    #[test]
+    fn dm_is_visible_to_teachers_and_its_student_only() {
+        let (student, other, teacher) = (user(), user(), user());
+        let dm = message(&student, None, true);
+
+        assert!(can_view(&student, &Role::Student, &dm));
+        assert!(can_view(&teacher, &Role::Teacher, &dm));
+        assert!(!can_view(&other, &Role::Student, &dm));
+        assert!(can_view(
+            &other,
+            &Role::Student,
+            &message(&student, None, false)
+        ));
+    }
+
+    #[test]
+    fn only_the_owner_is_a_permanent_teacher() {
+        let (owner, other) = (user(), user());
+        let room = room_owned_by(&owner);
+
+        assert_eq!(permanent_role(&room, &owner), Role::Teacher);
+        assert_eq!(permanent_role(&room, &other), Role::Student);
+    }
+
+    #[test]
+    fn students_see_only_the_active_session() {
+        let student = user();
+        let current = message(&student, None, false);
+        let past = message(&student, None, false);
+
+        assert!(can_view_session(
+            &Role::Student,
+            &current,
+            Some(&current.session_id)
+        ));
+        assert!(!can_view_session(
+            &Role::Student,
+            &past,
+            Some(&current.session_id)
+        ));
+        assert!(!can_view_session(&Role::Student, &past, None));
+        assert!(can_view_session(
+            &Role::Teacher,
+            &past,
+            Some(&current.session_id)
+        ));
+    }
+
+    #[test]
+    fn thread_student_is_the_student_side_of_the_dm() {
+        let (student, teacher) = (user(), user());
+        assert_eq!(thread_student(&message(&student, None, true)), &student);
+        assert_eq!(
+            thread_student(&message(&teacher, Some(&student), true)),
+            &student
+        );
+    }
+
+    #[test]
+    fn replies_only_to_root_dms() {
+        let student = user();
+        let root = message(&student, None, true);
+        assert_eq!(check_reply_parent(&root), Ok(()));
+
+        assert_eq!(
+            check_reply_parent(&message(&student, None, false)),
+            Err(ReplyViolation::NotADm)
+        );
+
+        let mut reply = message(&student, None, true);
+        reply.parent_message_id = Some(root.id.clone());
+        assert_eq!(check_reply_parent(&reply), Err(ReplyViolation::NestedReply));
+    }
+
+    #[test]
+    fn teacher_must_name_a_recipient() {
+        assert_eq!(
+            route_dm(&Role::Teacher, None),
+            Err(DmViolation::RecipientRequired)
+        );
+    }
+}
@@ -0,0 +1,193 @@
+//! 自分のルーム一覧 (作成したルーム / 参加しているルーム)
+
+use axum::extract::State;
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, sea_query::Expr, ColumnTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect, RelationTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use ts_rs::TS;
+
+use crate::auth::AuthUser;
+use crate::entities::{message, room, room_member, room_session, session_teacher, user};
+use crate::error::AppError;
+use crate::extract::{Json, Query};
+use crate::{policy, sync_user, AppState};
+
+/// 1ページの既定件数と上限
+const DEFAULT_ROOMS_LIMIT: u64 = 20;
+const MAX_ROOMS_LIMIT: u64 = 100;
+
+/// 一覧の対象
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "../../frontend/types/generated/room_scope.ts")]
+pub enum RoomScope {
+    /// 自分が作成したルーム
+    Owned,
+    /// 他の人が作成し、自分が参加しているルーム
+    #[default]
+    Joined,
+}
+
+#[derive(Deserialize)]
+pub struct RoomListQuery {
+    #[serde(default)]
+    scope: RoomScope,
+    /// 0 始まりのページ番号
+    page: Option<u64>,
+    limit: Option<u64>,
+}
+
+/// 一覧に表示するルームの情報
+#[derive(Serialize, Clone, Debug, TS)]
+#[ts(export, export_to = "../../frontend/types/generated/room_summary.ts")]
+pub struct RoomSummary {
+    pub room: room::Model,
+    /// 現在のセッションでの自分の権限
+    pub role: room_member::Role,
+    pub member_count: u32,
+    /// 最後にメッセージが送られた日時 (まだなければ null)
+    pub last_activity_at: Option<DateTimeWithTimeZone>,
+    /// 進行中のセッション (まだ始まっていなければ null)
+    pub active_session: Option<room_session::Model>,
+}
+
+/// ルーム一覧の1ページ (新しく作られた順)
+#[derive(Serialize, Clone, Debug, TS)]
+#[ts(export, export_to = "../../frontend/types/generated/room_list.ts")]
+pub struct RoomList {
+    pub rooms: Vec<RoomSummary>,
+    /// 次のページがあるか
+    pub has_more: bool,
+}
+
+/// 自分のルーム一覧を返すハンドラ
+pub async fn list_rooms_handler(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Query(query): Query<RoomListQuery>,
+) -> Result<Json<RoomList>, AppError> {
+    let user_id = sync_user(&state.conn, &claims).await?;
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_ROOMS_LIMIT)
+        .clamp(1, MAX_ROOMS_LIMIT);
+    let page = query.page.unwrap_or(0);
+
+    let list = fetch_rooms(&state.conn, &user_id, query.scope, page, limit).await?;
+
+    Ok(Json(list))
+}
+
+async fn fetch_rooms(
+    conn: &DatabaseConnection,
+    user_id: &user::UserId,
+    scope: RoomScope,
+    page: u64,
+    limit: u64,
+) -> Result<RoomList, sea_orm::DbErr> {
+    let owner_filter = match scope {
+        RoomScope::Owned => room::Column::OwnerId.eq(user_id.clone()),
+        RoomScope::Joined => room::Column::OwnerId.ne(user_id.clone()),
+    };
+
+    // 大きすぎる page でも溢れないようにする (Postgres の OFFSET は bigint)
+    let offset = page.saturating_mul(limit).min(i64::MAX as u64);
+
+    // 1件多く取って、次のページがあるか判定する
+    let mut rooms = room::Entity::find()
+        .join(sea_orm::JoinType::InnerJoin, room::Relation::Members.def())
+        .filter(room_member::Column::UserId.eq(user_id.clone()))
+        .filter(owner_filter)
+        .order_by_desc(room::Column::CreatedAt)
+        .order_by_desc(room::Column::Id)
+        .offset(offset)
+        .limit(limit + 1)
+        .all(conn)
+        .await?;
+
+    let has_more = rooms.len() as u64 > limit;
+    rooms.truncate(limit as usize);
+
+    let room_ids = rooms.iter().map(|r| r.id.clone()).collect::<Vec<_>>();
+
+    // ルームごとのメンバー数
+    let member_counts = room_member::Entity::find()
+        .select_only()
+        .column(room_member::Column::RoomId)
+        .column_as(
+            Expr::col(room_member::Column::UserId).count(),
+            "member_count",
+        )
+        .filter(room_member::Column::RoomId.is_in(room_ids.clone()))
+        .group_by(room_member::Column::RoomId)
+        .into_tuple::<(room::RoomId, i64)>()
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|(id, count)| (id.0, count as u32))
+        .collect::<HashMap<_, _>>();
+
+    // ルームごとの最後のメッセージ
+    let last_activity = message::Entity::find()
+        .select_only()
+        .column(message::Column::RoomId)
+        .column_as(Expr::col(message::Column::SentAt).max(), "last_activity_at")
+        .filter(message::Column::RoomId.is_in(room_ids.clone()))
+        .group_by(message::Column::RoomId)
+        .into_tuple::<(room::RoomId, Option<DateTimeWithTimeZone>)>()
+        .all(conn)
+        .await?
+        .into_iter()
+        .filter_map(|(id, at)| Some((id.0, at?)))
+        .collect::<HashMap<_, _>>();
+
+    // 進行中のセッションと、そこで自分が教員に指定されているか
+    let mut active_sessions = room_session::Entity::find()
+        .filter(room_session::Column::RoomId.is_in(room_ids))
+        .filter(room_session::Column::EndedAt.is_null())
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|s| (s.room_id.0, s))
+        .collect::<HashMap<_, _>>();
+
+    let granted_sessions = session_teacher::Entity::find()
+        .filter(
+            session_teacher::Column::SessionId
+                .is_in(active_sessions.values().map(|s| s.id.clone())),
+        )
+        .filter(session_teacher::Column::UserId.eq(user_id.clone()))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|g| g.session_id.0)
+        .collect::<HashSet<_>>();
+
+    let rooms = rooms
+        .into_iter()
+        .map(|room| {
+            let active_session = active_sessions.remove(&room.id.0);
+            let granted = active_session
+                .as_ref()
+                .is_some_and(|s| granted_sessions.contains(&s.id.0));
+            let role = match policy::permanent_role(&room, user_id) {
+                room_member::Role::Student if granted => room_member::Role::Teacher,
+                role => role,
+            };
+
+            RoomSummary {
+                role,
+                member_count: member_counts.get(&room.id.0).copied().unwrap_or(0),
+                last_activity_at: last_activity.get(&room.id.0).copied(),
+                active_session,
+                room,
+            }
+        })
+        .collect();
+
+    Ok(RoomList { rooms, has_more })
+}
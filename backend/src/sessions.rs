@@ -0,0 +1,182 @@
+//! チャットセッション (教員の「履歴クリア」で区切られるチャットの単位)
+
+use axum::extract::State;
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+
+use crate::auth::AuthUser;
+use crate::entities::{room, room_member, room_session, session_teacher, user};
+use crate::error::AppError;
+use crate::extract::{Json, Path};
+use crate::policy;
+use crate::ws::{
+    self,
+    protocol::ServerEvent,
+    routing::{Audience, RoleChange},
+};
+use crate::{find_room_membership, sync_user, AppState};
+
+/// 進行中のセッションを取得する (なければ None)
+pub async fn find_active<C: ConnectionTrait>(
+    conn: &C,
+    room_id: &room::RoomId,
+) -> Result<Option<room_session::Model>, sea_orm::DbErr> {
+    room_session::Entity::find()
+        .filter(room_session::Column::RoomId.eq(room_id.clone()))
+        .filter(room_session::Column::EndedAt.is_null())
+        .one(conn)
+        .await
+}
+
+/// 現在のセッションでの実効権限
+/// ルーム作成者は常に教員、それ以外は進行中のセッションで教員に指定されていれば教員
+pub async fn effective_role<C: ConnectionTrait>(
+    conn: &C,
+    room: &room::Model,
+    user_id: &user::UserId,
+) -> Result<room_member::Role, sea_orm::DbErr> {
+    if policy::permanent_role(room, user_id) == room_member::Role::Teacher {
+        return Ok(room_member::Role::Teacher);
+    }
+
+    let granted = session_teacher::Entity::find()
+        .inner_join(room_session::Entity)
+        .filter(room_session::Column::RoomId.eq(room.id.clone()))
+        .filter(room_session::Column::EndedAt.is_null())
+        .filter(session_teacher::Column::UserId.eq(user_id.clone()))
+        .one(conn)
+        .await?
+        .is_some();
+
+    Ok(if granted {
+        room_member::Role::Teacher
+    } else {
+        room_member::Role::Student
+    })
+}
+
+/// 進行中のセッションを返す。なければ新しく始める
+pub async fn current_or_start(
+    conn: &DatabaseConnection,
+    room_id: &room::RoomId,
+) -> Result<room_session::Model, sea_orm::DbErr> {
+    if let Some(active) = find_active(conn, room_id).await? {
+        return Ok(active);
+    }
+    insert_active(conn, room_id).await
+}
+
+/// 進行中のセッションを終了し、新しいセッションを始める (「履歴クリア」)
+/// 終了したセッションのメッセージはログとして残り、追加教員の指定は引き継がない
+pub async fn rollover(
+    conn: &DatabaseConnection,
+    room_id: &room::RoomId,
+) -> Result<room_session::Model, sea_orm::DbErr> {
+    let txn = conn.begin().await?;
+
+    room_session::Entity::update_many()
+        .col_expr(
+            room_session::Column::EndedAt,
+            Expr::value(chrono::Utc::now().fixed_offset()),
+        )
+        .filter(room_session::Column::RoomId.eq(room_id.clone()))
+        .filter(room_session::Column::EndedAt.is_null())
+        .exec(&txn)
+        .await?;
+
+    let session = insert_active(&txn, room_id).await?;
+    txn.commit().await?;
+
+    Ok(session)
+}
+
+/// 新しいセッションを始め、接続中の全員に SessionStarted を配信する
+/// 追加教員だった接続は学生の権限に戻る
+pub async fn restart(
+    state: &AppState,
+    room_id: &room::RoomId,
+) -> Result<room_session::Model, sea_orm::DbErr> {
+    let session = rollover(&state.conn, room_id).await?;
+
+    let event = ServerEvent::SessionStarted {
+        session: session.clone(),
+    };
+    ws::broadcast(
+        &state.ws_state,
+        room_id,
+        Audience::Everyone,
+        Some(RoleChange::RevertGrants),
+        &event,
+    )
+    .await;
+
+    Ok(session)
+}
+
+/// 進行中のセッションを作る
+/// 同時に作られた場合は idx_room_sessions_active で片方が無視されるので、残った方を返す
+async fn insert_active<C: ConnectionTrait>(
+    conn: &C,
+    room_id: &room::RoomId,
+) -> Result<room_session::Model, sea_orm::DbErr> {
+    room_session::Entity::insert(room_session::ActiveModel {
+        id: Set(room_session::RoomSessionId(uuid::Uuid::now_v7())),
+        room_id: Set(room_id.clone()),
+        started_at: Set(chrono::Utc::now().into()),
+        ended_at: Set(None),
+    })
+    .on_conflict(OnConflict::new().do_nothing().to_owned())
+    .exec_without_returning(conn)
+    .await?;
+
+    find_active(conn, room_id)
+        .await?
+        .ok_or_else(|| sea_orm::DbErr::RecordNotFound("Active session".to_string()))
+}
+
+/// セッション一覧を新しい順に返すハンドラ (教員のみ)
+pub async fn list_sessions_handler(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(slug): Path<String>,
+) -> Result<Json<Vec<room_session::Model>>, AppError> {
+    let user_id = sync_user(&state.conn, &claims).await?;
+
+    let (target_room, member) = find_room_membership(&state.conn, &slug, &user_id).await?;
+    require_teacher(&member)?;
+
+    let sessions = room_session::Entity::find()
+        .filter(room_session::Column::RoomId.eq(target_room.id))
+        .order_by_desc(room_session::Column::StartedAt)
+        .all(&state.conn)
+        .await?;
+
+    Ok(Json(sessions))
+}
+
+/// 現在のセッションを終了して新しいセッションを始めるハンドラ (教員のみ)
+/// 接続中の全員に SessionStarted を配信し、表示中のチャットをクリアさせる
+pub async fn start_session_handler(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(slug): Path<String>,
+) -> Result<Json<room_session::Model>, AppError> {
+    let user_id = sync_user(&state.conn, &claims).await?;
+
+    let (target_room, member) = find_room_membership(&state.conn, &slug, &user_id).await?;
+    require_teacher(&member)?;
+
+    let session = restart(&state, &target_room.id).await?;
+
+    Ok(Json(session))
+}
+
+fn require_teacher(member: &room_member::Model) -> Result<(), AppError> {
+    match member.role {
+        room_member::Role::Teacher => Ok(()),
+        room_member::Role::Student => Err(AppError::TeacherOnly("manage sessions")),
+    }
+}
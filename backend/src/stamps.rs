@@ -0,0 +1,103 @@
+//! スタンプ (リアクション) のカタログと検証
+
+use serde::Serialize;
+use ts_rs::TS;
+
+use crate::extract::Json;
+
+/// reactions.emoji カラムの最大文字数 (VARCHAR(10))
+const MAX_EMOJI_LEN: usize = 10;
+
+/// システムであらかじめ用意しているスタンプ
+#[derive(Serialize, Clone, Debug, TS)]
+#[ts(export, export_to = "../../frontend/types/generated/stamp.ts")]
+pub struct Stamp {
+    /// reactions.emoji に保存される識別子
+    pub id: &'static str,
+    pub label: &'static str,
+    pub emoji: &'static str,
+}
+
+/// 仕様書のスタンプ一覧 (教員・学生ともに使用可能)
+pub const SYSTEM_STAMPS: &[Stamp] = &[
+    Stamp {
+        id: "correct",
+        label: "正解",
+        emoji: "✔️",
+    },
+    Stamp {
+        id: "incorrect",
+        label: "不正解",
+        emoji: "❌",
+    },
+    Stamp {
+        id: "like",
+        label: "いいね",
+        emoji: "👍",
+    },
+    Stamp {
+        id: "idea",
+        label: "ひらめき",
+        emoji: "💡",
+    },
+    Stamp {
+        id: "thinking",
+        label: "考え中",
+        emoji: "🤔",
+    },
+    Stamp {
+        id: "great",
+        label: "素晴らしい",
+        emoji: "👏",
+    },
+    Stamp {
+        id: "checking",
+        label: "確認中",
+        emoji: "🔍",
+    },
+];
+
+/// スタンプ一覧を返すハンドラ
+pub async fn list_stamps_handler() -> Json<&'static [Stamp]> {
+    Json(SYSTEM_STAMPS)
+}
+
+/// リアクションとして保存してよい文字列か
+/// カタログのスタンプID、または emoji カラムに収まる絵文字1つだけを受け付ける
+pub fn is_valid_reaction(value: &str) -> bool {
+    if SYSTEM_STAMPS.iter().any(|s| s.id == value) {
+        return true;
+    }
+
+    value.chars().count() <= MAX_EMOJI_LEN && emojis::get(value).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalog_ids_fit_the_emoji_column() {
+        for stamp in SYSTEM_STAMPS {
+            assert!(stamp.id.chars().count() <= MAX_EMOJI_LEN, "{}", stamp.id);
+            assert!(is_valid_reaction(stamp.id));
+        }
+    }
+
+    #[test]
+    fn accepts_single_emoji_clusters() {
+        assert!(is_valid_reaction("👍"));
+        assert!(is_valid_reaction("👍🏽"));
+        assert!(is_valid_reaction("🇯🇵"));
+        assert!(is_valid_reaction("👨‍👩‍👧‍👦"));
+    }
+
+    #[test]
+    fn rejects_arbitrary_strings() {
+        assert!(!is_valid_reaction(""));
+        assert!(!is_valid_reaction("正解"));
+        assert!(!is_valid_reaction("hello"));
+        assert!(!is_valid_reaction("👍👍"));
+        assert!(!is_valid_reaction("<script>"));
+    }
+}
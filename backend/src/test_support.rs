@@ -0,0 +1,43 @@
+//! テスト用の共通フィクスチャ
+
+use crate::entities::message;
+use crate::entities::room::{self, RoomId};
+use crate::entities::room_session::RoomSessionId;
+use crate::entities::user::UserId;
+
+pub fn user() -> UserId {
+    UserId(uuid::Uuid::now_v7())
+}
+
+pub fn room() -> RoomId {
+    RoomId(uuid::Uuid::now_v7())
+}
+
+/// `owner` が作成した進行中のルーム
+pub fn room_owned_by(owner: &UserId) -> room::Model {
+    let now = chrono::Utc::now();
+    room::Model {
+        id: room(),
+        slug: "algebra1".to_string(),
+        name: "代数学".to_string(),
+        owner_id: owner.clone(),
+        is_active: true,
+        created_at: now.into(),
+        updated_at: now.into(),
+    }
+}
+
+/// 新しいルームの全体チャットまたはDMのメッセージ (返信ではない)
+pub fn message(sender: &UserId, recipient: Option<&UserId>, is_dm: bool) -> message::Model {
+    message::Model {
+        id: message::MessageId(uuid::Uuid::now_v7()),
+        room_id: RoomId(uuid::Uuid::now_v7()),
+        sender_id: sender.clone(),
+        content: "Aです".to_string(),
+        recipient_id: recipient.cloned(),
+        is_dm,
+        sent_at: chrono::Utc::now().into(),
+        parent_message_id: None,
+        session_id: RoomSessionId(uuid::Uuid::now_v7()),
+    }
+}
@@ -0,0 +1,78 @@
+//! WebSocket の設定 (環境変数で変更できる)
+
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+pub struct WsConfig {
+    /// ルームごとの broadcast チャンネルに溜めておける配信の数 (`WS_CHANNEL_CAPACITY`, 1 以上)
+    /// これを超えて遅れた接続は取りこぼした分を DB から送り直す
+    pub channel_capacity: usize,
+    /// サーバーから ping を送る間隔 (`WS_PING_INTERVAL_SECS`, 1 以上)
+    pub ping_interval: Duration,
+    /// 応答のない ping がこの回数続いたら切断する (`WS_MAX_MISSED_PONGS`, 1 以上)
+    pub max_missed_pongs: u32,
+    /// トークンの期限切れのどれだけ前に再認証を求めるか (`WS_REAUTH_LEAD_SECS`)
+    pub reauth_lead: Duration,
+}
+
+impl Default for WsConfig {
+    fn default() -> Self {
+        Self {
+            channel_capacity: 100,
+            ping_interval: Duration::from_secs(30),
+            max_missed_pongs: 2,
+            reauth_lead: Duration::from_secs(300),
+        }
+    }
+}
+
+impl WsConfig {
+    /// 環境変数から読み込む。設定されていない項目は既定値を使う
+    /// 0 にできない項目が 0 なら起動時に panic する
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            channel_capacity: positive_env_or("WS_CHANNEL_CAPACITY", default.channel_capacity),
+            ping_interval: Duration::from_secs(positive_env_or(
+                "WS_PING_INTERVAL_SECS",
+                default.ping_interval.as_secs(),
+            )),
+            max_missed_pongs: positive_env_or("WS_MAX_MISSED_PONGS", default.max_missed_pongs),
+            reauth_lead: Duration::from_secs(env_or(
+                "WS_REAUTH_LEAD_SECS",
+                default.reauth_lead.as_secs(),
+            )),
+        }
+    }
+}
+
+fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    match std::env::var(key) {
+        Ok(value) if !value.is_empty() => value
+            .parse()
+            .unwrap_or_else(|_| panic!("{} is not a valid number", key)),
+        _ => default,
+    }
+}
+
+/// 1 以上でなければならない設定値 (broadcast チャンネルの容量、ping の間隔など)
+fn positive_env_or<T: FromStr + PartialOrd + From<u8>>(key: &str, default: T) -> T {
+    let value = env_or(key, default);
+    if value < T::from(1) {
+        panic!("{} must be at least 1", key);
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "WS_TEST_ZERO_CAPACITY must be at least 1")]
+    fn rejects_zero_for_positive_settings() {
+        std::env::set_var("WS_TEST_ZERO_CAPACITY", "0");
+        positive_env_or("WS_TEST_ZERO_CAPACITY", 100usize);
+    }
+}
@@ -0,0 +1,100 @@
+//! ルームごとの broadcast チャンネル
+//! 接続数を数えておき、最後の接続が抜けたらチャンネルごと破棄する
+
+use std::collections::HashMap;
+use tokio::sync::broadcast;
+
+use super::routing::Outbound;
+use crate::entities::room::RoomId;
+
+/// 1つのルームの配信チャンネルと、それを購読している接続の数
+struct RoomHub {
+    tx: broadcast::Sender<Outbound>,
+    subscribers: usize,
+}
+
+#[derive(Default)]
+pub struct Hubs {
+    rooms: HashMap<RoomId, RoomHub>,
+}
+
+impl Hubs {
+    /// ルームの配信を購読する。最初の接続なら `capacity` 件溜められるチャンネルを作る
+    pub fn subscribe(
+        &mut self,
+        room_id: &RoomId,
+        capacity: usize,
+    ) -> broadcast::Receiver<Outbound> {
+        let hub = self
+            .rooms
+            .entry(room_id.clone())
+            .or_insert_with(|| RoomHub {
+                tx: broadcast::channel(capacity).0,
+                subscribers: 0,
+            });
+        hub.subscribers += 1;
+        hub.tx.subscribe()
+    }
+
+    /// 購読をやめる。最後の接続ならチャンネルを破棄して true を返す
+    pub fn unsubscribe(&mut self, room_id: &RoomId) -> bool {
+        let Some(hub) = self.rooms.get_mut(room_id) else {
+            return false;
+        };
+
+        hub.subscribers = hub.subscribers.saturating_sub(1);
+        if hub.subscribers > 0 {
+            return false;
+        }
+
+        self.rooms.remove(room_id);
+        true
+    }
+
+    /// ルームの送信側 (接続がひとつもなければ None)
+    pub fn sender(&self, room_id: &RoomId) -> Option<&broadcast::Sender<Outbound>> {
+        self.rooms.get(room_id).map(|hub| &hub.tx)
+    }
+
+    /// 接続が残っていてもチャンネルを破棄する (ルームの削除時)
+    /// 返した送信側を捨てると、各接続は溜まっている配信を読み切った後に終了する
+    pub fn remove(&mut self, room_id: &RoomId) -> Option<broadcast::Sender<Outbound>> {
+        self.rooms.remove(room_id).map(|hub| hub.tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::room;
+
+    #[test]
+    fn drops_the_channel_when_the_last_subscriber_leaves() {
+        let mut hubs = Hubs::default();
+        let room_id = room();
+
+        let _first = hubs.subscribe(&room_id, 4);
+        let _second = hubs.subscribe(&room_id, 4);
+        assert_eq!(hubs.sender(&room_id).unwrap().receiver_count(), 2);
+
+        assert!(!hubs.unsubscribe(&room_id));
+        assert!(hubs.sender(&room_id).is_some());
+
+        assert!(hubs.unsubscribe(&room_id));
+        assert!(hubs.sender(&room_id).is_none());
+        assert!(hubs.rooms.is_empty());
+    }
+
+    #[test]
+    fn ignores_leaving_a_removed_room() {
+        let mut hubs = Hubs::default();
+        let room_id = room();
+
+        let _rx = hubs.subscribe(&room_id, 4);
+        assert!(hubs.remove(&room_id).is_some());
+
+        // 削除後に接続が抜けても、作り直したりはしない
+        assert!(!hubs.unsubscribe(&room_id));
+        assert!(hubs.rooms.is_empty());
+    }
+}
@@ -0,0 +1,62 @@
+//! WebSocket の運用上のカウンタ (`GET /api/ws/metrics` で取得できる)
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Default)]
+pub struct WsMetrics {
+    /// broadcast の配信に追いつけなかった回数
+    lagged_events: AtomicU64,
+    /// それによって捨てられた配信の数
+    skipped_messages: AtomicU64,
+}
+
+/// カウンタのその時点の値
+#[derive(Serialize, Debug, PartialEq)]
+pub struct WsMetricsSnapshot {
+    pub lagged_events: u64,
+    pub skipped_messages: u64,
+}
+
+impl WsMetrics {
+    /// 配信の遅れを記録する
+    pub fn record_lag(&self, skipped: u64) {
+        self.lagged_events.fetch_add(1, Ordering::Relaxed);
+        self.skipped_messages.fetch_add(skipped, Ordering::Relaxed);
+    }
+
+    pub fn lagged_events(&self) -> u64 {
+        self.lagged_events.load(Ordering::Relaxed)
+    }
+
+    pub fn skipped_messages(&self) -> u64 {
+        self.skipped_messages.load(Ordering::Relaxed)
+    }
+
+    pub fn snapshot(&self) -> WsMetricsSnapshot {
+        WsMetricsSnapshot {
+            lagged_events: self.lagged_events(),
+            skipped_messages: self.skipped_messages(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_lag_events_and_skipped_messages() {
+        let metrics = WsMetrics::default();
+        metrics.record_lag(3);
+        metrics.record_lag(5);
+
+        assert_eq!(
+            metrics.snapshot(),
+            WsMetricsSnapshot {
+                lagged_events: 2,
+                skipped_messages: 8,
+            }
+        );
+    }
+}
@@ -0,0 +1,1352 @@
+use axum::{
+    extract::{
+        ws::{CloseFrame, Message, WebSocketUpgrade},
+        State,
+    },
+    response::Response,
+};
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelTrait, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter,
+    Set,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, mpsc, watch, Mutex};
+use ts_rs::TS;
+
+pub mod config;
+pub mod hub;
+pub mod metrics;
+pub mod presence;
+pub mod protocol;
+pub mod routing;
+pub mod ticket;
+
+use crate::auth::AuthUser;
+use crate::entities::{message, reaction, room, room_member, room_session, user};
+use crate::error::AppError;
+use crate::extract::{Json, Path, Query};
+use crate::members::MemberInfo;
+use crate::policy::{self, DmRoute, DmViolation, ReplyViolation};
+use crate::{find_room_membership, sync_user, AppState};
+use crate::{messages, sessions, stamps};
+use config::WsConfig;
+use hub::Hubs;
+use metrics::{WsMetrics, WsMetricsSnapshot};
+use presence::Presence;
+use protocol::{close_code, ClientEvent, ServerEvent, WsErrorCode, WsMessagePayload};
+use routing::{Audience, Disconnect, Outbound, RoleChange};
+use ticket::{Tickets, TICKET_TTL};
+
+/// 接続直後に送る履歴の件数
+const INITIAL_HISTORY_LIMIT: u64 = 50;
+/// 再接続時に差分として再送する最大件数 (超えたら履歴を送り直す)
+const MAX_REPLAY: u64 = 500;
+
+#[derive(Default)]
+pub struct WsState {
+    /// ルームごとの配信チャンネル (接続がなくなったルームの分は残さない)
+    pub rooms: Mutex<Hubs>,
+    /// ルームごと・ユーザーごとの接続数 (複数タブを数える)
+    pub presence: Mutex<Presence>,
+    /// 発行済みの接続用チケット
+    pub tickets: Mutex<Tickets>,
+    pub config: WsConfig,
+    pub metrics: WsMetrics,
+}
+
+impl WsState {
+    pub fn new(config: WsConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+}
+
+/// WebSocket 接続用チケットの発行結果
+#[derive(Serialize, TS)]
+#[ts(
+    export,
+    export_to = "../../frontend/types/generated/ws_ticket_response.ts"
+)]
+pub struct WsTicketResponse {
+    /// 接続時に `?ticket=` で渡す。一度しか使えない
+    pub ticket: String,
+    /// 有効期間 (秒)
+    pub expires_in: u32,
+}
+
+/// このルームへの WebSocket 接続に使うチケットを発行するハンドラ
+pub async fn ws_ticket_handler(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(slug): Path<String>,
+) -> Result<Json<WsTicketResponse>, AppError> {
+    let user_id = sync_user(&state.conn, &claims).await?;
+
+    // 参加していないルームのチケットは発行しない (接続時にもう一度確認する)
+    let (target_room, _) = find_room_membership(&state.conn, &slug, &user_id).await?;
+
+    let ticket =
+        state
+            .ws_state
+            .tickets
+            .lock()
+            .await
+            .issue(user_id, target_room.id, claims.exp as u64);
+
+    Ok(Json(WsTicketResponse {
+        ticket,
+        expires_in: TICKET_TTL.as_secs() as u32,
+    }))
+}
+
+/// WebSocket の運用カウンタ (送信の遅れで読み飛ばした回数など) を返すハンドラ
+pub async fn ws_metrics_handler(State(state): State<AppState>) -> Json<WsMetricsSnapshot> {
+    Json(state.ws_state.metrics.snapshot())
+}
+
+#[derive(Deserialize)]
+pub struct WsQuery {
+    /// `ws_ticket_handler` で発行したチケット
+    ticket: String,
+    /// 再接続時、最後に受け取ったメッセージのID (それ以降をサーバーが再送する)
+    last_message_id: Option<message::MessageId>,
+}
+
+pub async fn ws_handler(
+    ws: WebSocketUpgrade,
+    Path(slug): Path<String>,
+    Query(query): Query<WsQuery>,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    // 1. チケットを使い切る (未発行・使用済み・期限切れなら 401)
+    let ticket = state
+        .ws_state
+        .tickets
+        .lock()
+        .await
+        .redeem(&query.ticket)
+        .ok_or(AppError::Unauthorized(
+            "Invalid or expired ticket".to_string(),
+        ))?;
+
+    // 2. チケットを発行したユーザーを取得 (のちほどメッセージ送信者を特定するため)
+    let current_user = user::Entity::find_by_id(ticket.user_id)
+        .one(&state.conn)
+        .await?
+        .ok_or(AppError::Unauthorized("User not found".to_string()))?;
+
+    // 3. 該当の部屋が存在し、ユーザーが参加メンバーか確認 (なければ 404 / 403)
+    let (target_room, current_member) =
+        find_room_membership(&state.conn, &slug, &current_user.id).await?;
+
+    // チケットは発行したルームにだけ使える
+    if ticket.room_id != target_room.id {
+        return Err(AppError::Unauthorized(
+            "Ticket was issued for another room".to_string(),
+        ));
+    }
+
+    // 4. 終了したルームには学生は入れない (教員はログの閲覧や再開のために入れる)
+    if !target_room.is_active && current_member.role != room_member::Role::Teacher {
+        return Err(AppError::RoomEnded);
+    }
+
+    // 5. 教員が入った時点で、進行中のセッションがなければ新しく始める
+    if current_member.role == room_member::Role::Teacher {
+        sessions::current_or_start(&state.conn, &target_room.id).await?;
+    }
+
+    // 6. WebSocketのコネクションにアップグレード
+    // アップグレードが成功したら `handle_socket` という非同期タスクに処理を移譲します
+    // 検証済みのルーム・ユーザー・メンバー情報をそのまま渡すので、handle_socket 側で再取得は不要
+    let last_message_id = query.last_message_id;
+    Ok(ws.on_upgrade(move |socket| {
+        handle_socket(
+            socket,
+            state,
+            target_room,
+            current_user,
+            current_member,
+            ticket.token_expires_at,
+            last_message_id,
+        )
+    }))
+}
+
+/// 1本の WebSocket 接続が扱う情報
+struct Session {
+    state: AppState,
+    room: room::Model,
+    user: user::Model,
+    member: room_member::Model,
+    /// 現在の実効権限 (追加教員の指定・解除で接続中に変わる。書き込むのは送信タスク)
+    role: watch::Receiver<room_member::Role>,
+    /// ルームが開いているか (接続時の値から、終了・再開の配信で送信タスクが更新する)
+    room_active: AtomicBool,
+    /// この接続だけに返すイベント (ack / error / pong)
+    direct_tx: mpsc::UnboundedSender<ServerEvent>,
+    /// 応答のないまま送った ping の数 (クライアントから何か届くたびに 0 に戻る)
+    missed_pongs: AtomicU32,
+    /// 認証トークンの期限 (UNIX 秒)。再認証で延びる
+    expires_at: watch::Sender<u64>,
+}
+
+async fn handle_socket<S>(
+    socket: S,
+    state: AppState,
+    target_room: room::Model,
+    current_user: user::Model,
+    current_member: room_member::Model,
+    token_expires_at: u64,
+    last_message_id: Option<message::MessageId>,
+) where
+    S: Sink<Message> + Stream<Item = Result<Message, axum::Error>> + Send + 'static,
+{
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+
+    let capacity = state.ws_state.config.channel_capacity;
+    let rx = state
+        .ws_state
+        .rooms
+        .lock()
+        .await
+        .subscribe(&target_room.id, capacity);
+    // 接続中として登録し、そのユーザーの最初のタブならルームの全員に知らせる
+    let ws_state = state.ws_state.clone();
+    let room_id = target_room.id.clone();
+    let came_online = ws_state
+        .presence
+        .lock()
+        .await
+        .connect(&room_id, &current_user.id);
+    if came_online {
+        let joined = ServerEvent::MemberJoined {
+            member: MemberInfo::new(
+                &current_user,
+                &current_member,
+                current_member.role.clone(),
+                true,
+            ),
+        };
+        broadcast(&ws_state, &room_id, Audience::Everyone, None, &joined).await;
+    }
+
+    let (direct_tx, mut direct_rx) = mpsc::unbounded_channel::<ServerEvent>();
+    let (role_tx, role_rx) = watch::channel(current_member.role.clone());
+    let base_role = policy::permanent_role(&target_room, &current_user.id);
+
+    let user_id = current_user.id.clone();
+    let slug = target_room.slug.clone();
+    let session = Arc::new(Session {
+        state,
+        room_active: AtomicBool::new(target_room.is_active),
+        room: target_room,
+        user: current_user,
+        member: current_member,
+        role: role_rx,
+        direct_tx,
+        missed_pongs: AtomicU32::new(0),
+        expires_at: watch::Sender::new(token_expires_at),
+    });
+
+    // 購読を始めてから DB を読むので、読んでいる間の配信は rx に溜まり取りこぼさない
+    // 履歴 (または再接続時の差分) を送り終えてから送信タスクを始め、順序も保つ
+    let (backfill, backfilled) = session.backfill(last_message_id).await;
+    let send_timeout = ws_state.config.ping_interval;
+    if let Ok(json) = serde_json::to_string(&backfill) {
+        if !send_within(&mut ws_sender, Message::Text(json.into()), send_timeout).await {
+            leave_room(&ws_state, &room_id, &user_id).await;
+            return;
+        }
+    }
+
+    // 送信タスク: ルーム全体への配信と、この接続宛てのイベントをまとめて流す
+    // DMは宛先に含まれない接続にはそもそも書き込まない (クライアント側で隠すのではない)
+    let send_session = session.clone();
+    let mut send_task = tokio::spawn(async move {
+        let session = send_session;
+        let conn_user_id = session.user.id.clone();
+        let mut rx = rx;
+        let mut backfilled = backfilled;
+        // 配信の中で最後に見たメッセージ (遅れたときにここから先を DB から送り直す)
+        let mut last_seen = latest(&backfilled);
+        // 最初の ping は1周期待ってから送る
+        let config = &session.state.ws_state.config;
+        let mut heartbeat = tokio::time::interval_at(
+            tokio::time::Instant::now() + config.ping_interval,
+            config.ping_interval,
+        );
+        heartbeat.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // トークンの期限が近づいたら再認証を求め、期限までに来なければ切断する
+        let mut expires_at = session.expires_at.subscribe();
+        let mut reauth_requested = false;
+        loop {
+            let deadline = {
+                let expires_at = *expires_at.borrow_and_update();
+                if reauth_requested {
+                    instant_at(expires_at, Duration::ZERO)
+                } else {
+                    instant_at(expires_at, config.reauth_lead)
+                }
+            };
+            let (texts, disconnect) = tokio::select! {
+                msg = rx.recv() => match msg {
+                    Ok(out) => {
+                        // 権限の変更は宛先の判定より先に反映し、変わった場合だけ本人に知らせる
+                        // (Set はこの配信自体が RoleChanged なので改めて知らせない)
+                        let new_role = out
+                            .role_change
+                            .as_ref()
+                            .and_then(|change| change.apply(&conn_user_id, &base_role));
+                        if let Some(active) = out.room_active {
+                            session.room_active.store(active, Ordering::Relaxed);
+                        }
+                        if let Some(role) = new_role {
+                            let announced =
+                                matches!(out.role_change, Some(RoleChange::Set { .. }));
+                            if update_role(&role_tx, role.clone()) && !announced {
+                                session.reply(ServerEvent::RoleChanged {
+                                    user_id: conn_user_id.clone(),
+                                    role,
+                                });
+                            }
+                        }
+
+                        if out.message_id.is_some() {
+                            last_seen = out.message_id.clone();
+                        }
+                        // 履歴として送り済みのメッセージは二重に送らない
+                        if out.message_id.as_ref().is_some_and(|id| backfilled.contains(id)) {
+                            continue;
+                        }
+                        if !out.audience.includes(&conn_user_id, &role_tx.borrow()) {
+                            continue;
+                        }
+                        (vec![out.json], out.disconnect)
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        // 配信に追いつけず古い分が捨てられた。切断せず、取りこぼした範囲を DB から送り直す
+                        let metrics = &session.state.ws_state.metrics;
+                        metrics.record_lag(skipped);
+                        eprintln!(
+                            "WebSocket of user {:?} lagged behind by {} messages (total: {} lags, {} skipped)",
+                            conn_user_id,
+                            skipped,
+                            metrics.lagged_events(),
+                            metrics.skipped_messages(),
+                        );
+
+                        // 捨てられた中に権限の変更があったかもしれないので DB から取り直す
+                        let role = sessions::effective_role(
+                            &session.state.conn,
+                            &session.room,
+                            &conn_user_id,
+                        )
+                        .await;
+                        match role {
+                            Ok(role) => {
+                                if update_role(&role_tx, role.clone()) {
+                                    session.reply(ServerEvent::RoleChanged {
+                                        user_id: conn_user_id.clone(),
+                                        role,
+                                    });
+                                }
+                            }
+                            Err(e) => eprintln!("Failed to refresh role after lag: {}", e),
+                        }
+                        // ルームの終了・再開も捨てられたかもしれない
+                        match room::Entity::find_by_id(session.room.id.clone())
+                            .one(&session.state.conn)
+                            .await
+                        {
+                            Ok(Some(room)) => session.room_active.store(room.is_active, Ordering::Relaxed),
+                            Ok(None) => {}
+                            Err(e) => eprintln!("Failed to refresh room status after lag: {}", e),
+                        }
+
+                        let (backfill, ids) = session.backfill(last_seen.clone()).await;
+                        if let Some(latest) = latest(&ids) {
+                            last_seen = Some(latest);
+                        }
+                        backfilled = ids;
+
+                        let texts = [
+                            ServerEvent::Resync {
+                                skipped: u32::try_from(skipped).unwrap_or(u32::MAX),
+                            },
+                            backfill,
+                        ]
+                            .iter()
+                            .filter_map(|event| serde_json::to_string(event).ok())
+                            .collect();
+                        (texts, None)
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                Some(event) = direct_rx.recv() => match serde_json::to_string(&event) {
+                    Ok(json) => (vec![json], None),
+                    Err(_) => continue,
+                },
+                // 再認証で期限が延びたので、待つ時刻を計算し直す
+                Ok(()) = expires_at.changed() => {
+                    reauth_requested = false;
+                    continue;
+                }
+                _ = tokio::time::sleep_until(deadline) => {
+                    if reauth_requested {
+                        println!("🔒 Token of user {:?} expired without reauth", conn_user_id);
+                        let frame = CloseFrame {
+                            code: close_code::TOKEN_EXPIRED,
+                            reason: "Token expired".into(),
+                        };
+                        send_within(&mut ws_sender, Message::Close(Some(frame)), send_timeout).await;
+                        break;
+                    }
+
+                    reauth_requested = true;
+                    let event = ServerEvent::ReauthRequired {
+                        expires_at: *session.expires_at.borrow(),
+                    };
+                    match serde_json::to_string(&event) {
+                        Ok(json) => (vec![json], None),
+                        Err(_) => continue,
+                    }
+                }
+                _ = heartbeat.tick() => {
+                    // 応答のない ping が続いたら、半開きの接続とみなして切断する
+                    let missed = session.missed_pongs.fetch_add(1, Ordering::Relaxed);
+                    if missed >= config.max_missed_pongs {
+                        println!("💀 WebSocket of user {:?} stopped responding", conn_user_id);
+                        let frame = CloseFrame {
+                            code: close_code::HEARTBEAT_TIMEOUT,
+                            reason: "Heartbeat timeout".into(),
+                        };
+                        send_within(&mut ws_sender, Message::Close(Some(frame)), send_timeout).await;
+                        break;
+                    }
+
+                    // プロトコルの ping (ブラウザが自動で pong を返す) と、アプリケーションの ping を送る
+                    if !send_within(&mut ws_sender, Message::Ping(Default::default()), send_timeout).await {
+                        break;
+                    }
+                    match serde_json::to_string(&ServerEvent::Ping) {
+                        Ok(json) => (vec![json], None),
+                        Err(_) => continue,
+                    }
+                }
+            };
+
+            for text in texts {
+                if !send_within(&mut ws_sender, Message::Text(text.into()), send_timeout).await {
+                    return;
+                }
+            }
+
+            // イベントを届けてからクローズコード付きで切断する
+            if let Some(disconnect) = disconnect {
+                let frame = CloseFrame {
+                    code: disconnect.code,
+                    reason: disconnect.reason.into(),
+                };
+                send_within(&mut ws_sender, Message::Close(Some(frame)), send_timeout).await;
+                break;
+            }
+        }
+    });
+
+    // 受信タスク
+    let mut recv_task = tokio::spawn(async move {
+        while let Some(Ok(msg)) = ws_receiver.next().await {
+            // どんなフレームでも届けば接続は生きている
+            session.missed_pongs.store(0, Ordering::Relaxed);
+
+            let text = match msg {
+                Message::Text(text) => text,
+                Message::Binary(_) => {
+                    session.reply(ServerEvent::error(
+                        WsErrorCode::InvalidPayload,
+                        "Binary frames are not supported",
+                    ));
+                    continue;
+                }
+                Message::Close(_) => break,
+                // Ping フレームには axum が自動で応答する。Pong フレームは上で生存の記録だけする
+                _ => continue,
+            };
+
+            // 不正なフレームはチャット本文として保存せず、エラーを返す
+            let event = match serde_json::from_str::<ClientEvent>(&text) {
+                Ok(event) => event,
+                Err(e) => {
+                    session.reply(ServerEvent::error(
+                        WsErrorCode::InvalidPayload,
+                        e.to_string(),
+                    ));
+                    continue;
+                }
+            };
+
+            if let Err((code, message)) = session.handle_event(event).await {
+                session.reply(ServerEvent::error(code, message));
+            }
+        }
+    });
+
+    tokio::select! {
+        _ = (&mut send_task) => recv_task.abort(),
+        _ = (&mut recv_task) => send_task.abort(),
+    };
+
+    leave_room(&ws_state, &room_id, &user_id).await;
+
+    println!("👋 User {:?} disconnected from room: {}", user_id, slug);
+}
+
+/// 1フレームを送る。失敗したときや `limit` 以内に送れなかったときは false
+/// 読み取らなくなった相手 (半開きの接続) は送信バッファが埋まると書き込みが進まなくなるので、
+/// 待ち続けずに切れた接続として扱う
+async fn send_within<S>(ws_sender: &mut S, message: Message, limit: Duration) -> bool
+where
+    S: Sink<Message> + Unpin,
+{
+    matches!(
+        tokio::time::timeout(limit, ws_sender.send(message)).await,
+        Ok(Ok(()))
+    )
+}
+
+/// 接続中の一覧から外し、最後のタブだったら残っている接続に知らせる
+/// 最後の接続だったらルームの配信チャンネルも破棄する
+async fn leave_room(ws_state: &WsState, room_id: &room::RoomId, user_id: &user::UserId) {
+    let went_offline = ws_state.presence.lock().await.disconnect(room_id, user_id);
+    if went_offline {
+        let left = ServerEvent::MemberLeft {
+            user_id: user_id.clone(),
+        };
+        broadcast(ws_state, room_id, Audience::Everyone, None, &left).await;
+    }
+
+    ws_state.rooms.lock().await.unsubscribe(room_id);
+}
+
+/// 権限を更新し、変わったかどうかを返す
+fn update_role(role_tx: &watch::Sender<room_member::Role>, role: room_member::Role) -> bool {
+    role_tx.send_if_modified(|current| {
+        let changed = *current != role;
+        *current = role;
+        changed
+    })
+}
+
+/// UNIX 秒 `unix_secs` の `lead` 前にあたる時刻 (過ぎていれば今)
+fn instant_at(unix_secs: u64, lead: Duration) -> tokio::time::Instant {
+    let target = UNIX_EPOCH + Duration::from_secs(unix_secs);
+    let remaining = target
+        .checked_sub(lead)
+        .and_then(|at| at.duration_since(SystemTime::now()).ok())
+        .unwrap_or_default();
+    tokio::time::Instant::now() + remaining
+}
+
+/// メッセージIDのうち最も新しいもの
+fn latest(ids: &HashSet<message::MessageId>) -> Option<message::MessageId> {
+    ids.iter().max_by_key(|id| id.0).cloned()
+}
+
+impl Session {
+    /// 現在の実効権限
+    fn role(&self) -> room_member::Role {
+        self.role.borrow().clone()
+    }
+
+    /// 履歴の絞り込みに使う、現在の権限を反映したメンバー情報
+    fn viewer(&self) -> room_member::Model {
+        room_member::Model {
+            role: self.role(),
+            ..self.member.clone()
+        }
+    }
+
+    /// この接続だけにイベントを返す
+    fn reply(&self, event: ServerEvent) {
+        let _ = self.direct_tx.send(event);
+    }
+
+    /// 接続直後に送るイベントと、そこに含めたメッセージのID
+    /// `last_message_id` があればそれ以降の差分 (Replay)、なければ直近の履歴 (History)
+    async fn backfill(
+        &self,
+        last_message_id: Option<message::MessageId>,
+    ) -> (ServerEvent, HashSet<message::MessageId>) {
+        match self.load_backfill(last_message_id).await {
+            Ok(backfill) => backfill,
+            Err(e) => {
+                eprintln!("Failed to load message history: {}", e);
+                (
+                    ServerEvent::error(WsErrorCode::Internal, "Failed to load message history"),
+                    HashSet::new(),
+                )
+            }
+        }
+    }
+
+    async fn load_backfill(
+        &self,
+        last_message_id: Option<message::MessageId>,
+    ) -> Result<(ServerEvent, HashSet<message::MessageId>), sea_orm::DbErr> {
+        let conn = &self.state.conn;
+        let ids_of =
+            |messages: &[WsMessagePayload]| messages.iter().map(|m| m.id.clone()).collect();
+
+        // まだセッションが始まっていなければ空の履歴を送る
+        let Some(active) = sessions::find_active(conn, &self.room.id).await? else {
+            let history = messages::MessageHistory {
+                messages: Vec::new(),
+                has_more: false,
+            };
+            return Ok((ServerEvent::History(history), HashSet::new()));
+        };
+
+        // このルームの進行中のセッションのメッセージでなければ (削除済み・別ルーム・履歴クリア後) 履歴を取り直す
+        let anchor = match last_message_id {
+            Some(id) => {
+                message::Entity::find_by_id(id)
+                    .filter(message::Column::RoomId.eq(self.room.id.clone()))
+                    .filter(message::Column::SessionId.eq(active.id.clone()))
+                    .one(conn)
+                    .await?
+            }
+            None => None,
+        };
+
+        if let Some(anchor) = anchor {
+            let replay = messages::fetch_after(
+                conn,
+                &self.room.id,
+                &active.id,
+                &self.viewer(),
+                &anchor.id,
+                MAX_REPLAY,
+            )
+            .await?;
+            if let Some(messages) = replay {
+                let ids = ids_of(&messages);
+                return Ok((ServerEvent::Replay { messages }, ids));
+            }
+        }
+
+        let history = messages::fetch_history(
+            conn,
+            &self.room.id,
+            &active.id,
+            &self.viewer(),
+            None,
+            INITIAL_HISTORY_LIMIT,
+        )
+        .await?;
+        let ids = ids_of(&history.messages);
+        Ok((ServerEvent::History(history), ids))
+    }
+
+    /// ルームの接続のうち、audience に含まれるものにイベントを配信する
+    async fn broadcast(&self, audience: Audience, event: &ServerEvent) {
+        broadcast(&self.state.ws_state, &self.room.id, audience, None, event).await;
+    }
+
+    async fn handle_event(&self, event: ClientEvent) -> Result<(), (WsErrorCode, String)> {
+        if !matches!(
+            event,
+            ClientEvent::Ping | ClientEvent::Pong | ClientEvent::Reauth { .. }
+        ) {
+            self.ensure_writable()?;
+        }
+
+        match event {
+            ClientEvent::SendMessage { content, client_id } => {
+                self.send_message(content, client_id).await
+            }
+            ClientEvent::SendDm {
+                content,
+                recipient_id,
+                client_id,
+            } => self.send_dm(content, recipient_id, client_id).await,
+            ClientEvent::Reply {
+                parent_message_id,
+                content,
+                client_id,
+            } => self.send_reply(parent_message_id, content, client_id).await,
+            ClientEvent::React { message_id, emoji } => {
+                self.set_reaction(message_id, emoji, true).await
+            }
+            ClientEvent::Unreact { message_id, emoji } => {
+                self.set_reaction(message_id, emoji, false).await
+            }
+            ClientEvent::StartSession => self.start_session().await,
+            ClientEvent::Ping => {
+                self.reply(ServerEvent::Pong);
+                Ok(())
+            }
+            // 生存の記録は受信時に済んでいる
+            ClientEvent::Pong => Ok(()),
+            ClientEvent::Reauth { token } => self.reauth(token).await,
+        }
+    }
+
+    /// 新しいトークンを検証し、接続の期限を延ばす
+    /// 接続したユーザー本人のトークンでなければ受け付けない
+    async fn reauth(&self, token: String) -> Result<(), (WsErrorCode, String)> {
+        let claims = self
+            .state
+            .auth
+            .verify_token(&token)
+            .await
+            .map_err(|e| (WsErrorCode::InvalidToken, e.to_string()))?;
+
+        if claims.sub != self.user.firebase_uid {
+            return Err((
+                WsErrorCode::InvalidToken,
+                "Token belongs to a different user".to_string(),
+            ));
+        }
+
+        let expires_at = claims.exp as u64;
+        self.expires_at.send_replace(expires_at);
+        self.reply(ServerEvent::Reauthenticated { expires_at });
+        Ok(())
+    }
+
+    async fn send_message(
+        &self,
+        content: String,
+        client_id: Option<String>,
+    ) -> Result<(), (WsErrorCode, String)> {
+        let new_message = NewMessage {
+            content,
+            recipient_id: None,
+            is_dm: false,
+            parent_message_id: None,
+        };
+        self.deliver(new_message, client_id).await
+    }
+
+    /// DMを送る
+    /// 学生→教員DMは教員全員に、教員→学生DMは指定した学生と教員全員に届く
+    async fn send_dm(
+        &self,
+        content: String,
+        recipient_id: Option<user::UserId>,
+        client_id: Option<String>,
+    ) -> Result<(), (WsErrorCode, String)> {
+        let recipient = match &recipient_id {
+            Some(id) => Some(self.find_member(id).await?),
+            None => None,
+        };
+
+        // 送信者と宛先の権限の組み合わせを検証する (学生同士・教員同士は不可)
+        let route = policy::route_dm(&self.role(), recipient.as_ref().map(|m| &m.role))
+            .map_err(dm_violation)?;
+
+        let recipient_id = match route {
+            DmRoute::ToTeachers => None,
+            DmRoute::ToStudent => recipient_id,
+        };
+
+        let new_message = NewMessage {
+            content,
+            recipient_id,
+            is_dm: true,
+            parent_message_id: None,
+        };
+        self.deliver(new_message, client_id).await
+    }
+
+    /// 現在のセッションを終了して新しいセッションを始める (教員のみ)
+    async fn start_session(&self) -> Result<(), (WsErrorCode, String)> {
+        if self.role() != room_member::Role::Teacher {
+            return Err((
+                WsErrorCode::TeacherOnly,
+                "Only teachers can start a new session".to_string(),
+            ));
+        }
+
+        sessions::restart(&self.state, &self.room.id)
+            .await
+            .map_err(internal("Failed to start session"))?;
+
+        Ok(())
+    }
+
+    /// DMのスレッドに返信する
+    /// 元のDMと同じ相手 (当事者の学生と教員全員) に届く
+    async fn send_reply(
+        &self,
+        parent_message_id: message::MessageId,
+        content: String,
+        client_id: Option<String>,
+    ) -> Result<(), (WsErrorCode, String)> {
+        let active = self.active_session().await?;
+
+        let parent = message::Entity::find_by_id(parent_message_id.clone())
+            .filter(message::Column::RoomId.eq(self.room.id.clone()))
+            .one(&self.state.conn)
+            .await
+            .map_err(internal("Failed to load message"))?
+            // 見えないDMの存在は明かさない
+            .filter(|m| policy::can_view(&self.user.id, &self.role(), m))
+            // 過去のセッションのスレッドには返信できない
+            .filter(|m| active.as_ref() == Some(&m.session_id))
+            .ok_or((
+                WsErrorCode::ParentNotFound,
+                "Parent message not found".to_string(),
+            ))?;
+
+        policy::check_reply_parent(&parent).map_err(|v| {
+            let code = match v {
+                ReplyViolation::NotADm => WsErrorCode::ReplyToNonDm,
+                ReplyViolation::NestedReply => WsErrorCode::NestedReply,
+            };
+            (code, v.to_string())
+        })?;
+
+        // 教員からの返信は当事者の学生宛て、学生からの返信は教員全員宛て
+        let recipient_id = match self.role() {
+            room_member::Role::Teacher => Some(policy::thread_student(&parent).clone()),
+            room_member::Role::Student => None,
+        };
+
+        let new_message = NewMessage {
+            content,
+            recipient_id,
+            is_dm: true,
+            parent_message_id: Some(parent.id),
+        };
+        self.deliver(new_message, client_id).await
+    }
+
+    /// スタンプを付ける / 外す
+    /// 変更はそのメッセージを閲覧できる接続にだけ配信する (DMのスタンプを漏らさない)
+    async fn set_reaction(
+        &self,
+        message_id: message::MessageId,
+        emoji: String,
+        added: bool,
+    ) -> Result<(), (WsErrorCode, String)> {
+        // カタログのスタンプか絵文字1つだけ (任意の文字列は不可)
+        if !stamps::is_valid_reaction(&emoji) {
+            return Err((WsErrorCode::InvalidEmoji, "Invalid emoji".to_string()));
+        }
+
+        let active = self.active_session().await?;
+
+        let target = message::Entity::find_by_id(message_id.clone())
+            .filter(message::Column::RoomId.eq(self.room.id.clone()))
+            .one(&self.state.conn)
+            .await
+            .map_err(internal("Failed to load message"))?
+            .filter(|m| policy::can_view(&self.user.id, &self.role(), m))
+            .filter(|m| policy::can_view_session(&self.role(), m, active.as_ref()))
+            .ok_or((
+                WsErrorCode::MessageNotFound,
+                "Message not found".to_string(),
+            ))?;
+
+        let changed = if added {
+            // 同じスタンプを二重に付けても unique_reaction 制約で無視される
+            reaction::Entity::insert(reaction::ActiveModel {
+                id: Set(reaction::ReactionId(uuid::Uuid::now_v7())),
+                message_id: Set(target.id.clone()),
+                user_id: Set(self.user.id.clone()),
+                emoji: Set(emoji.clone()),
+                created_at: Set(chrono::Utc::now().into()),
+            })
+            .on_conflict(
+                OnConflict::columns([
+                    reaction::Column::MessageId,
+                    reaction::Column::UserId,
+                    reaction::Column::Emoji,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .exec_without_returning(&self.state.conn)
+            .await
+            .map_err(internal("Failed to save reaction"))?
+        } else {
+            reaction::Entity::delete_many()
+                .filter(reaction::Column::MessageId.eq(target.id.clone()))
+                .filter(reaction::Column::UserId.eq(self.user.id.clone()))
+                .filter(reaction::Column::Emoji.eq(emoji.clone()))
+                .exec(&self.state.conn)
+                .await
+                .map_err(internal("Failed to delete reaction"))?
+                .rows_affected
+        };
+
+        // 何も変わっていなければ配信しない
+        if changed == 0 {
+            return Ok(());
+        }
+
+        let count = reaction::Entity::find()
+            .filter(reaction::Column::MessageId.eq(target.id.clone()))
+            .filter(reaction::Column::Emoji.eq(emoji.clone()))
+            .count(&self.state.conn)
+            .await
+            .map_err(internal("Failed to count reactions"))?;
+
+        let event = ServerEvent::Reaction {
+            message_id: target.id.clone(),
+            emoji,
+            user_id: self.user.id.clone(),
+            added,
+            count: count as u32,
+        };
+        self.broadcast(Audience::for_message(&target), &event).await;
+
+        Ok(())
+    }
+
+    /// 終了したルームでは学生は閲覧のみ (接続中の終了・再開は配信で room_active に反映済み)
+    fn ensure_writable(&self) -> Result<(), (WsErrorCode, String)> {
+        if self.role() == room_member::Role::Teacher {
+            return Ok(());
+        }
+
+        if !self.room_active.load(Ordering::Relaxed) {
+            return Err((WsErrorCode::RoomEnded, "This room has ended".to_string()));
+        }
+        Ok(())
+    }
+
+    /// 進行中のセッションのID (まだ始まっていなければ None)
+    async fn active_session(
+        &self,
+    ) -> Result<Option<room_session::RoomSessionId>, (WsErrorCode, String)> {
+        Ok(sessions::find_active(&self.state.conn, &self.room.id)
+            .await
+            .map_err(internal("Failed to load session"))?
+            .map(|s| s.id))
+    }
+
+    /// 同じルームのメンバーを取得する (いなければ RecipientNotFound)
+    /// role は現在のセッションでの実効権限に置き換えて返す
+    async fn find_member(
+        &self,
+        user_id: &user::UserId,
+    ) -> Result<room_member::Model, (WsErrorCode, String)> {
+        let mut member = room_member::Entity::find()
+            .filter(room_member::Column::RoomId.eq(self.room.id.clone()))
+            .filter(room_member::Column::UserId.eq(user_id.clone()))
+            .one(&self.state.conn)
+            .await
+            .map_err(internal("Failed to load member"))?
+            .ok_or((
+                WsErrorCode::RecipientNotFound,
+                "Recipient is not a member of this room".to_string(),
+            ))?;
+
+        member.role = sessions::effective_role(&self.state.conn, &self.room, user_id)
+            .await
+            .map_err(internal("Failed to load member"))?;
+
+        Ok(member)
+    }
+
+    /// メッセージを保存し、閲覧できる接続に配信して ack を返す
+    /// DMは送信者自身 (別タブ含む)・宛先の学生・教員全員にだけ届く
+    async fn deliver(
+        &self,
+        new_message: NewMessage,
+        client_id: Option<String>,
+    ) -> Result<(), (WsErrorCode, String)> {
+        if new_message.content.trim().is_empty() {
+            return Err((WsErrorCode::EmptyMessage, "Message is empty".to_string()));
+        }
+
+        // 進行中のセッションに紐づけて保存 (教員より先に学生が送った場合はここでセッションが始まる)
+        let session = sessions::current_or_start(&self.state.conn, &self.room.id)
+            .await
+            .map_err(internal("Failed to load session"))?;
+
+        let saved = message::ActiveModel {
+            id: Set(message::MessageId(uuid::Uuid::now_v7())),
+            room_id: Set(self.room.id.clone()),
+            sender_id: Set(self.user.id.clone()),
+            content: Set(new_message.content),
+            recipient_id: Set(new_message.recipient_id),
+            is_dm: Set(new_message.is_dm),
+            sent_at: Set(chrono::Utc::now().into()),
+            parent_message_id: Set(new_message.parent_message_id),
+            session_id: Set(session.id),
+        }
+        .insert(&self.state.conn)
+        .await
+        .map_err(internal("Failed to save message"))?;
+
+        let message_id = saved.id.clone();
+        let audience = Audience::for_message(&saved);
+
+        // フロントエンドに送るJSONペイロードを作成
+        let payload = WsMessagePayload::new(saved, Some(&self.user), self.role());
+        let event = if payload.is_dm {
+            ServerEvent::Dm(payload)
+        } else {
+            ServerEvent::Message(payload)
+        };
+        self.broadcast(audience, &event).await;
+        self.reply(ServerEvent::Ack {
+            client_id,
+            message_id,
+        });
+
+        Ok(())
+    }
+}
+
+/// ルームの接続のうち、audience に含まれるものにイベントを配信する
+/// `role_change` は宛先の判定より先に、受け取った各接続の権限に反映される
+/// 接続がひとつもなければ何もしない (REST のハンドラからも使う)
+pub async fn broadcast(
+    ws_state: &WsState,
+    room_id: &room::RoomId,
+    audience: Audience,
+    role_change: Option<RoleChange>,
+    event: &ServerEvent,
+) {
+    // JSON文字列に変換
+    if let Ok(json) = serde_json::to_string(event) {
+        let rooms = ws_state.rooms.lock().await;
+        if let Some(tx) = rooms.sender(room_id) {
+            let _ = tx.send(Outbound {
+                audience,
+                message_id: event.message_id().cloned(),
+                role_change,
+                room_active: event.room_active(),
+                disconnect: None,
+                json,
+            });
+        }
+    }
+}
+
+/// ルームに接続中のユーザー
+pub async fn online_users(ws_state: &WsState, room_id: &room::RoomId) -> HashSet<user::UserId> {
+    ws_state.presence.lock().await.online(room_id)
+}
+
+/// ルームの接続すべてにイベントを届けてから切断し、ルームの broadcast チャンネルを破棄する
+/// ルームの削除時に使う
+pub async fn close_room(
+    ws_state: &WsState,
+    room_id: &room::RoomId,
+    event: &ServerEvent,
+    disconnect: Disconnect,
+) {
+    // 送信側を外して捨てると、各接続は溜まっている配信を読み切った後に終了する
+    let removed = ws_state.rooms.lock().await.remove(room_id);
+    if let Some(tx) = removed {
+        if let Ok(json) = serde_json::to_string(event) {
+            let _ = tx.send(Outbound {
+                audience: Audience::Everyone,
+                message_id: None,
+                role_change: None,
+                room_active: None,
+                disconnect: Some(disconnect),
+                json,
+            });
+        }
+    }
+}
+
+/// 保存前のメッセージ
+struct NewMessage {
+    content: String,
+    recipient_id: Option<user::UserId>,
+    is_dm: bool,
+    parent_message_id: Option<message::MessageId>,
+}
+
+/// DBエラーをログに残し、ソケットには詳細を返さない
+fn internal(context: &'static str) -> impl Fn(sea_orm::DbErr) -> (WsErrorCode, String) {
+    move |e| {
+        eprintln!("{}: {}", context, e);
+        (WsErrorCode::Internal, context.to_string())
+    }
+}
+
+/// DMの権限違反をソケットに返すエラーに変換する
+fn dm_violation(violation: DmViolation) -> (WsErrorCode, String) {
+    let code = match violation {
+        DmViolation::StudentToStudent => WsErrorCode::StudentToStudentDm,
+        DmViolation::TeacherToTeacher => WsErrorCode::TeacherToTeacherDm,
+        DmViolation::RecipientRequired => WsErrorCode::RecipientRequired,
+    };
+    (code, violation.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::keys::{FileKeySource, KeyStore};
+    use crate::auth::FirebaseAuth;
+    use crate::test_support;
+    use sea_orm::{ConnectOptions, Database};
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// 書き込もうとすると失敗する (相手がもういない) ソケット。何も受信しない
+    struct ClosedSocket;
+
+    impl Stream for ClosedSocket {
+        type Item = Result<Message, axum::Error>;
+
+        fn poll_next(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Poll::Pending
+        }
+    }
+
+    impl Sink<Message> for ClosedSocket {
+        type Error = axum::Error;
+
+        fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Err(axum::Error::new("connection closed")))
+        }
+
+        fn start_send(self: Pin<&mut Self>, _: Message) -> Result<(), Self::Error> {
+            Err(axum::Error::new("connection closed"))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// 最初の `capacity` フレームだけ受け取り、あとは読み取らなくなるソケット (送信バッファが埋まった半開きの接続)
+    struct StalledSocket {
+        capacity: usize,
+    }
+
+    impl Stream for StalledSocket {
+        type Item = Result<Message, axum::Error>;
+
+        fn poll_next(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Poll::Pending
+        }
+    }
+
+    impl Sink<Message> for StalledSocket {
+        type Error = axum::Error;
+
+        fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.capacity > 0 {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn start_send(mut self: Pin<&mut Self>, _: Message) -> Result<(), Self::Error> {
+            self.capacity -= 1;
+            Ok(())
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Pending
+        }
+    }
+
+    fn token_expires_in_an_hour() -> u64 {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        now.as_secs() + 3600
+    }
+
+    /// DB には繋がらない状態 (履歴の代わりにエラーイベントを送ることになる)
+    async fn unreachable_state(config: WsConfig) -> AppState {
+        let mut options = ConnectOptions::new("postgres://127.0.0.1:1/unreachable");
+        options
+            .connect_lazy(true)
+            .acquire_timeout(Duration::from_millis(100));
+        AppState {
+            conn: Database::connect(options).await.unwrap(),
+            ws_state: Arc::new(WsState::new(config)),
+            auth: Arc::new(FirebaseAuth::new(
+                "test-project",
+                KeyStore::new(FileKeySource::new("testdata/test_jwks.json")),
+            )),
+        }
+    }
+
+    /// 学生として参加しているルームとユーザー
+    fn student_in_room() -> (room::Model, user::Model, room_member::Model) {
+        let now = chrono::Utc::now();
+        let student = user::Model {
+            id: test_support::user(),
+            firebase_uid: "student-uid".to_string(),
+            email: None,
+            display_name: None,
+            photo_url: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+        };
+        let target_room = test_support::room_owned_by(&test_support::user());
+        let member = room_member::Model {
+            room_id: target_room.id.clone(),
+            user_id: student.id.clone(),
+            role: room_member::Role::Student,
+            joined_at: now.into(),
+        };
+        (target_room, student, member)
+    }
+
+    #[test]
+    fn latest_picks_the_newest_message_id() {
+        let older = message::MessageId(uuid::Uuid::now_v7());
+        let newer = message::MessageId(uuid::Uuid::now_v7());
+        let ids = HashSet::from([newer.clone(), older]);
+
+        assert_eq!(latest(&ids), Some(newer));
+        assert_eq!(latest(&HashSet::new()), None);
+    }
+
+    #[test]
+    fn instant_at_subtracts_the_lead_and_clamps_to_now() {
+        let now_secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let now = tokio::time::Instant::now();
+
+        // すでに過ぎた期限はすぐ
+        assert!(instant_at(now_secs - 60, Duration::ZERO) <= now + Duration::from_secs(1));
+
+        // 1時間後の期限の5分前は、およそ55分後
+        let at = instant_at(now_secs + 3600, Duration::from_secs(300));
+        let wait = at - now;
+        assert!(wait > Duration::from_secs(3290) && wait <= Duration::from_secs(3300));
+    }
+
+    #[tokio::test]
+    async fn close_room_notifies_subscribers_and_drops_the_channel() {
+        let state = WsState::default();
+        let room_id = room::RoomId(uuid::Uuid::now_v7());
+        let mut rx = state.rooms.lock().await.subscribe(&room_id, 4);
+
+        let disconnect = Disconnect {
+            code: close_code::ROOM_DELETED,
+            reason: "Room deleted",
+        };
+        close_room(
+            &state,
+            &room_id,
+            &ServerEvent::RoomDeleted,
+            disconnect.clone(),
+        )
+        .await;
+
+        assert!(state.rooms.lock().await.sender(&room_id).is_none());
+        let out = rx.recv().await.unwrap();
+        assert_eq!(out.disconnect, Some(disconnect));
+        assert_eq!(out.json, r#"{"type":"room_deleted"}"#);
+        // 送信側が捨てられているので、これ以上は何も届かない
+        assert!(rx.recv().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn leaves_the_room_when_the_first_send_fails() {
+        let state = unreachable_state(WsConfig::default()).await;
+        let (target_room, student, member) = student_in_room();
+        let room_id = target_room.id.clone();
+
+        handle_socket(
+            ClosedSocket,
+            state.clone(),
+            target_room,
+            student,
+            member,
+            u64::MAX,
+            None,
+        )
+        .await;
+
+        let ws_state = &state.ws_state;
+        assert!(ws_state.presence.lock().await.online(&room_id).is_empty());
+        assert!(ws_state.rooms.lock().await.sender(&room_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn reaps_peers_that_stop_reading() {
+        let state = unreachable_state(WsConfig {
+            ping_interval: Duration::from_millis(50),
+            ..WsConfig::default()
+        })
+        .await;
+        let (target_room, student, member) = student_in_room();
+        let room_id = target_room.id.clone();
+
+        // 履歴は受け取るが、その後の ping から書き込みが進まなくなる
+        let socket = StalledSocket { capacity: 1 };
+        let session = handle_socket(
+            socket,
+            state.clone(),
+            target_room,
+            student,
+            member,
+            token_expires_in_an_hour(),
+            None,
+        );
+        tokio::time::timeout(Duration::from_secs(5), session)
+            .await
+            .expect("a peer that stopped reading was not reaped");
+
+        let ws_state = &state.ws_state;
+        assert!(ws_state.presence.lock().await.online(&room_id).is_empty());
+        assert!(ws_state.rooms.lock().await.sender(&room_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn students_write_only_while_the_room_is_open() {
+        // DB には繋がらないので、書き込み可否を DB に問い合わせていれば Internal になる
+        let state = unreachable_state(WsConfig::default()).await;
+        let (mut target_room, student, member) = student_in_room();
+        target_room.is_active = false;
+
+        let (direct_tx, _direct_rx) = mpsc::unbounded_channel();
+        let (_role_tx, role_rx) = watch::channel(member.role.clone());
+        let session = Session {
+            state,
+            room_active: AtomicBool::new(target_room.is_active),
+            room: target_room,
+            user: student,
+            member,
+            role: role_rx,
+            direct_tx,
+            missed_pongs: AtomicU32::new(0),
+            expires_at: watch::Sender::new(token_expires_in_an_hour()),
+        };
+
+        let (code, _) = session.ensure_writable().unwrap_err();
+        assert_eq!(code, WsErrorCode::RoomEnded);
+
+        // 再開の配信を受け取った後は書き込める
+        let reopened = ServerEvent::RoomStatusChanged { is_active: true };
+        session
+            .room_active
+            .store(reopened.room_active().unwrap(), Ordering::Relaxed);
+        assert!(session.ensure_writable().is_ok());
+    }
+}
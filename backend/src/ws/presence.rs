@@ -0,0 +1,100 @@
+//! ルームごとの接続中ユーザーの台帳
+//! 同じユーザーが複数のタブで開いていても、最後のタブを閉じるまではオンラインのまま
+
+use std::collections::{HashMap, HashSet};
+
+use crate::entities::room::RoomId;
+use crate::entities::user::UserId;
+
+#[derive(Default)]
+pub struct Presence {
+    /// ルームごと・ユーザーごとの接続数
+    rooms: HashMap<RoomId, HashMap<UserId, usize>>,
+}
+
+impl Presence {
+    /// 接続を登録する。そのユーザーの最初の接続なら true (オンラインになった)
+    pub fn connect(&mut self, room_id: &RoomId, user_id: &UserId) -> bool {
+        let count = self
+            .rooms
+            .entry(room_id.clone())
+            .or_default()
+            .entry(user_id.clone())
+            .or_insert(0);
+        *count += 1;
+        *count == 1
+    }
+
+    /// 接続を外す。そのユーザーの最後の接続なら true (オフラインになった)
+    pub fn disconnect(&mut self, room_id: &RoomId, user_id: &UserId) -> bool {
+        let Some(users) = self.rooms.get_mut(room_id) else {
+            return false;
+        };
+        let Some(count) = users.get_mut(user_id) else {
+            return false;
+        };
+
+        *count -= 1;
+        if *count > 0 {
+            return false;
+        }
+
+        users.remove(user_id);
+        if users.is_empty() {
+            self.rooms.remove(room_id);
+        }
+        true
+    }
+
+    /// ルームに接続中のユーザー
+    pub fn online(&self, room_id: &RoomId) -> HashSet<UserId> {
+        self.rooms
+            .get(room_id)
+            .map(|users| users.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn stays_online_until_the_last_tab_closes() {
+        let mut presence = Presence::default();
+        let (room, user) = (test_support::room(), test_support::user());
+
+        assert!(presence.connect(&room, &user));
+        assert!(!presence.connect(&room, &user));
+        assert_eq!(presence.rooms[&room][&user], 2);
+
+        assert!(!presence.disconnect(&room, &user));
+        assert!(presence.online(&room).contains(&user));
+
+        assert!(presence.disconnect(&room, &user));
+        assert!(presence.online(&room).is_empty());
+        assert!(presence.rooms.is_empty());
+    }
+
+    #[test]
+    fn ignores_unknown_disconnects() {
+        let mut presence = Presence::default();
+        let (room, user) = (test_support::room(), test_support::user());
+        assert!(!presence.disconnect(&room, &user));
+    }
+
+    #[test]
+    fn tracks_rooms_independently() {
+        let mut presence = Presence::default();
+        let (room, user) = (test_support::room(), test_support::user());
+        let other_room = test_support::room();
+
+        presence.connect(&room, &user);
+        presence.connect(&other_room, &user);
+        presence.disconnect(&room, &user);
+
+        assert!(presence.online(&room).is_empty());
+        assert!(presence.online(&other_room).contains(&user));
+    }
+}
@@ -0,0 +1,337 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::entities::message::{self, MessageId};
+use crate::entities::room_member::Role;
+use crate::entities::room_session;
+use crate::entities::user::{self, UserId};
+use crate::members::MemberInfo;
+use crate::messages::MessageHistory;
+
+// 🌟 リアルタイムチャットでやり取りされるメッセージの型
+#[derive(Serialize, Deserialize, Clone, Debug, TS)]
+#[ts(export, export_to = "../../frontend/types/generated/ws_message.ts")]
+pub struct WsMessagePayload {
+    pub id: MessageId,
+    pub content: String,
+    pub sender_id: UserId,
+    pub sender_name: String,
+    pub sender_photo_url: Option<String>,
+    pub sender_role: Role,
+    pub recipient_id: Option<UserId>, // 教員→学生DMの宛先 (学生→教員DMと全体チャットは null)
+    pub is_dm: bool,
+    pub parent_message_id: Option<MessageId>, // スレッドの返信なら元メッセージ
+    pub sent_at: String,
+    pub reactions: Vec<ReactionSummary>, // 履歴取得時に集計して付与 (新着メッセージは空)
+}
+
+/// メッセージに付いたスタンプの集計
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, TS)]
+#[ts(
+    export,
+    export_to = "../../frontend/types/generated/reaction_summary.ts"
+)]
+pub struct ReactionSummary {
+    pub emoji: String,
+    pub count: u32,
+    pub user_ids: Vec<UserId>,
+}
+
+impl WsMessagePayload {
+    /// 保存済みのメッセージと送信者情報からペイロードを組み立てる
+    pub fn new(message: message::Model, sender: Option<&user::Model>, sender_role: Role) -> Self {
+        Self {
+            id: message.id,
+            content: message.content,
+            sender_id: message.sender_id,
+            sender_name: sender
+                .and_then(|u| u.display_name.clone())
+                .unwrap_or_else(|| "名無し".to_string()),
+            sender_photo_url: sender.and_then(|u| u.photo_url.clone()),
+            sender_role,
+            recipient_id: message.recipient_id,
+            is_dm: message.is_dm,
+            parent_message_id: message.parent_message_id,
+            sent_at: message.sent_at.to_rfc3339(),
+            reactions: Vec::new(),
+        }
+    }
+}
+
+/// クライアント → サーバーのイベント
+/// `type` フィールドで種類を判別するので、種類を増やしても既存の形式は壊れない
+#[derive(Deserialize, Debug, PartialEq, TS)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[ts(
+    export,
+    export_to = "../../frontend/types/generated/ws_client_event.ts"
+)]
+pub enum ClientEvent {
+    /// 全体チャットへの投稿
+    SendMessage {
+        content: String,
+        /// クライアント側で採番した一時ID (ack でそのまま返す)
+        #[serde(default)]
+        #[ts(optional)]
+        client_id: Option<String>,
+    },
+    /// DMの送信
+    /// 学生は宛先を省略して教員全員に、教員は宛先の学生を指定して送る
+    SendDm {
+        content: String,
+        #[serde(default)]
+        #[ts(optional)]
+        recipient_id: Option<UserId>,
+        #[serde(default)]
+        #[ts(optional)]
+        client_id: Option<String>,
+    },
+    /// DMへの返信 (スレッドは1階層のみ)
+    Reply {
+        parent_message_id: MessageId,
+        content: String,
+        #[serde(default)]
+        #[ts(optional)]
+        client_id: Option<String>,
+    },
+    /// メッセージにスタンプを付ける
+    React {
+        message_id: MessageId,
+        emoji: String,
+    },
+    /// 付けたスタンプを外す
+    Unreact {
+        message_id: MessageId,
+        emoji: String,
+    },
+    /// 現在のセッションを終了して新しいセッションを始める (「履歴クリア」、教員のみ)
+    StartSession,
+    /// アプリケーションレベルの疎通確認
+    Ping,
+    /// サーバーからの ping への応答
+    Pong,
+    /// `reauth_required` を受けて、新しい ID トークンを渡す
+    Reauth { token: String },
+}
+
+/// サーバー → クライアントのイベント
+#[derive(Serialize, Clone, Debug, TS)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[ts(
+    export,
+    export_to = "../../frontend/types/generated/ws_server_event.ts"
+)]
+pub enum ServerEvent {
+    /// 接続直後に送る直近の履歴 (古い順)。さらに古いものは REST で取得する
+    History(MessageHistory),
+    /// `last_message_id` を指定して再接続したとき、それ以降に届いていたメッセージ (古い順)
+    /// 件数が多すぎる場合は代わりに History が届く
+    Replay {
+        messages: Vec<WsMessagePayload>,
+    },
+    /// サーバーからの配信に追いつけず、`skipped` 件を取りこぼした
+    /// 続けて取りこぼした範囲の Replay (多すぎる場合は History) が届く
+    Resync {
+        skipped: u32,
+    },
+    /// 全体チャットのメッセージ
+    Message(WsMessagePayload),
+    /// DM (宛先の学生と教員全員にだけ届く)
+    Dm(WsMessagePayload),
+    /// スタンプの追加・削除 (メッセージを閲覧できる接続にだけ届く)
+    Reaction {
+        message_id: MessageId,
+        emoji: String,
+        user_id: UserId,
+        added: bool,
+        /// 変更後のこのスタンプの数
+        count: u32,
+    },
+    /// 新しいセッションが始まった。表示中のチャットをクリアする (過去のセッションはログとして残る)
+    SessionStarted {
+        session: room_session::Model,
+    },
+    /// メンバーがルームに接続した
+    MemberJoined {
+        member: MemberInfo,
+    },
+    /// メンバーの接続が切れた
+    MemberLeft {
+        user_id: UserId,
+    },
+    /// この接続の権限が変わった (追加教員の指定・解除、セッションの切り替え)
+    RoleChanged {
+        user_id: UserId,
+        role: Role,
+    },
+    /// ルームが終了 / 再開された。終了中、学生は閲覧のみ
+    RoomStatusChanged {
+        is_active: bool,
+    },
+    /// ルームが削除された。この後サーバーが接続を閉じる (`close_code::ROOM_DELETED`)
+    RoomDeleted,
+    /// 送信したイベントをサーバーが受理した
+    Ack {
+        client_id: Option<String>,
+        message_id: MessageId,
+    },
+    /// 送信したイベントが処理できなかった
+    Error {
+        code: WsErrorCode,
+        message: String,
+    },
+    Pong,
+    /// 認証トークンの期限 (`expires_at`, UNIX 秒) が近い。新しいトークンを `reauth` で送る
+    /// 期限までに届かなければ `close_code::TOKEN_EXPIRED` で切断される
+    ReauthRequired {
+        #[ts(type = "number")]
+        expires_at: u64,
+    },
+    /// `reauth` で送られたトークンを受け付けた。新しい期限は `expires_at`
+    Reauthenticated {
+        #[ts(type = "number")]
+        expires_at: u64,
+    },
+    /// サーバーからの疎通確認。`pong` を返す
+    /// (ブラウザからはプロトコルの ping が見えないので、接続が生きているかの判断にも使える)
+    Ping,
+}
+
+/// サーバーから WebSocket を閉じるときのクローズコード (アプリケーション用の 4000-4999)
+pub mod close_code {
+    /// 認証トークンの期限が切れ、再認証もされなかった
+    pub const TOKEN_EXPIRED: u16 = 4001;
+    /// ルームが削除された
+    pub const ROOM_DELETED: u16 = 4004;
+    /// ping への応答が続けて途絶えた
+    pub const HEARTBEAT_TIMEOUT: u16 = 4008;
+}
+
+/// `ServerEvent::Error` の機械判別用コード
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "../../frontend/types/generated/ws_error_code.ts")]
+pub enum WsErrorCode {
+    /// JSONとして解釈できない、または未知の `type`
+    InvalidPayload,
+    /// 本文が空
+    EmptyMessage,
+    /// 教員からのDMに宛先が指定されていない
+    RecipientRequired,
+    /// 宛先がこのルームのメンバーではない
+    RecipientNotFound,
+    /// 学生同士のDMは不可
+    StudentToStudentDm,
+    /// 教員同士のDMは不可
+    TeacherToTeacherDm,
+    /// 返信先のメッセージが見つからない (または閲覧できない)
+    ParentNotFound,
+    /// 全体チャットのメッセージには返信できない
+    ReplyToNonDm,
+    /// 返信への返信はできない (スレッドは1階層のみ)
+    NestedReply,
+    /// 対象のメッセージが見つからない (または閲覧できない)
+    MessageNotFound,
+    /// スタンプとして使えない文字列 (カタログのスタンプか絵文字1つのみ)
+    InvalidEmoji,
+    /// 教員だけが行える操作
+    TeacherOnly,
+    /// ルームが終了しているので学生は投稿できない
+    RoomEnded,
+    /// 再認証のトークンが無効 (期限切れ・別のユーザーのもの)
+    InvalidToken,
+    /// サーバー内部のエラー
+    Internal,
+}
+
+impl ServerEvent {
+    /// メッセージを運ぶイベントならそのID (再接続時の重複排除に使う)
+    pub fn message_id(&self) -> Option<&MessageId> {
+        match self {
+            ServerEvent::Message(payload) | ServerEvent::Dm(payload) => Some(&payload.id),
+            _ => None,
+        }
+    }
+
+    /// ルームの終了・再開を知らせるイベントなら、新しい状態 (各接続の書き込み可否に反映する)
+    pub fn room_active(&self) -> Option<bool> {
+        match self {
+            ServerEvent::RoomStatusChanged { is_active } => Some(*is_active),
+            _ => None,
+        }
+    }
+
+    pub fn error(code: WsErrorCode, message: impl Into<String>) -> Self {
+        ServerEvent::Error {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_tagged_client_events() {
+        let event: ClientEvent =
+            serde_json::from_value(json!({ "type": "send_message", "content": "こんにちは" }))
+                .unwrap();
+        assert_eq!(
+            event,
+            ClientEvent::SendMessage {
+                content: "こんにちは".to_string(),
+                client_id: None,
+            }
+        );
+
+        let event: ClientEvent =
+            serde_json::from_value(json!({ "type": "send_dm", "content": "Aです" })).unwrap();
+        assert_eq!(
+            event,
+            ClientEvent::SendDm {
+                content: "Aです".to_string(),
+                recipient_id: None,
+                client_id: None,
+            }
+        );
+
+        let event: ClientEvent = serde_json::from_value(json!({ "type": "ping" })).unwrap();
+        assert_eq!(event, ClientEvent::Ping);
+
+        let event: ClientEvent = serde_json::from_value(json!({ "type": "pong" })).unwrap();
+        assert_eq!(event, ClientEvent::Pong);
+
+        let event: ClientEvent =
+            serde_json::from_value(json!({ "type": "reauth", "token": "t" })).unwrap();
+        assert_eq!(
+            event,
+            ClientEvent::Reauth {
+                token: "t".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_raw_text_and_unknown_types() {
+        assert!(serde_json::from_str::<ClientEvent>("hello").is_err());
+        assert!(serde_json::from_value::<ClientEvent>(json!({ "type": "unknown" })).is_err());
+    }
+
+    #[test]
+    fn serializes_server_events_with_type_tag() {
+        let value =
+            serde_json::to_value(ServerEvent::error(WsErrorCode::InvalidPayload, "bad frame"))
+                .unwrap();
+        assert_eq!(
+            value,
+            json!({ "type": "error", "code": "invalid_payload", "message": "bad frame" })
+        );
+        assert_eq!(
+            serde_json::to_value(ServerEvent::Ping).unwrap(),
+            json!({ "type": "ping" })
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use crate::entities::message;
+use crate::entities::room_member::Role;
+use crate::entities::user::UserId;
+
+/// イベントを受け取ってよい接続の範囲
+/// 判定は接続ごとに行うので、同じユーザーが複数のタブで開いていればそのすべてに届く
+#[derive(Clone, Debug, PartialEq)]
+pub enum Audience {
+    /// ルームの全員 (全体チャット)
+    Everyone,
+    /// 教員全員 + 指定したユーザー (DM)
+    TeachersAnd(Vec<UserId>),
+}
+
+impl Audience {
+    /// メッセージ (とそれに付くスタンプ) を受け取れる範囲
+    /// DMは送信者・宛先と教員全員 (学生→教員DMでは送信者が当事者の学生)
+    pub fn for_message(message: &message::Model) -> Self {
+        if !message.is_dm {
+            return Audience::Everyone;
+        }
+        let mut users = vec![message.sender_id.clone()];
+        users.extend(message.recipient_id.clone());
+        Audience::TeachersAnd(users)
+    }
+
+    /// 接続中のユーザーがこのイベントを受け取ってよいか
+    pub fn includes(&self, user_id: &UserId, role: &Role) -> bool {
+        match self {
+            Audience::Everyone => true,
+            Audience::TeachersAnd(users) => *role == Role::Teacher || users.contains(user_id),
+        }
+    }
+}
+
+/// 配信を受け取った接続の権限の変更
+#[derive(Clone, Debug, PartialEq)]
+pub enum RoleChange {
+    /// セッションが切り替わり、追加教員の指定がすべて外れた (恒久的な権限に戻す)
+    RevertGrants,
+    /// ルーム作成者が指定したユーザーを教員に設定 / 解除した
+    Set { user_id: UserId, role: Role },
+}
+
+impl RoleChange {
+    /// この変更を適用した後の、接続中のユーザーの権限 (影響がなければ None)
+    /// `base_role` はセッションをまたいで変わらない権限
+    pub fn apply(&self, user_id: &UserId, base_role: &Role) -> Option<Role> {
+        match self {
+            RoleChange::RevertGrants => Some(base_role.clone()),
+            RoleChange::Set {
+                user_id: target,
+                role,
+            } if target == user_id => Some(role.clone()),
+            RoleChange::Set { .. } => None,
+        }
+    }
+}
+
+/// ルームの broadcast チャンネルに流す配信単位
+/// 宛先の判定は各接続の送信タスクで行い、対象外のソケットには書き込まない
+#[derive(Clone, Debug)]
+pub struct Outbound {
+    pub audience: Audience,
+    /// メッセージを運ぶイベントならそのID
+    pub message_id: Option<message::MessageId>,
+    /// 宛先の判定より先に、受け取った接続の権限に反映する変更
+    pub role_change: Option<RoleChange>,
+    /// ルームの終了・再開なら新しい状態 (受け取った接続が学生の書き込みを止める・再開する)
+    pub room_active: Option<bool>,
+    /// イベントを届けた後に接続を閉じる場合のクローズコードと理由
+    pub disconnect: Option<Disconnect>,
+    pub json: String,
+}
+
+/// サーバーから接続を閉じるときのクローズフレーム
+#[derive(Clone, Debug, PartialEq)]
+pub struct Disconnect {
+    pub code: u16,
+    pub reason: &'static str,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{message, user};
+
+    #[test]
+    fn dm_reaches_teachers_and_listed_users_only() {
+        let (student, other_student, teacher) = (user(), user(), user());
+        let audience = Audience::TeachersAnd(vec![student.clone()]);
+
+        assert!(audience.includes(&student, &Role::Student));
+        assert!(audience.includes(&teacher, &Role::Teacher));
+        assert!(!audience.includes(&other_student, &Role::Student));
+    }
+
+    #[test]
+    fn teacher_dm_audience_is_its_student_and_teachers() {
+        let (teacher, student, other_student) = (user(), user(), user());
+        let dm = message(&teacher, Some(&student), true);
+
+        let audience = Audience::for_message(&dm);
+        assert!(audience.includes(&student, &Role::Student));
+        assert!(audience.includes(&teacher, &Role::Teacher));
+        assert!(!audience.includes(&other_student, &Role::Student));
+    }
+
+    #[test]
+    fn role_change_targets_only_the_named_user() {
+        let (promoted, other) = (user(), user());
+        let change = RoleChange::Set {
+            user_id: promoted.clone(),
+            role: Role::Teacher,
+        };
+
+        assert_eq!(change.apply(&promoted, &Role::Student), Some(Role::Teacher));
+        assert_eq!(change.apply(&other, &Role::Student), None);
+    }
+
+    #[test]
+    fn revert_grants_falls_back_to_the_permanent_role() {
+        let owner = user();
+        assert_eq!(
+            RoleChange::RevertGrants.apply(&user(), &Role::Student),
+            Some(Role::Student)
+        );
+        assert_eq!(
+            RoleChange::RevertGrants.apply(&owner, &Role::Teacher),
+            Some(Role::Teacher)
+        );
+    }
+
+    #[test]
+    fn everyone_reaches_all() {
+        assert!(Audience::Everyone.includes(&user(), &Role::Student));
+    }
+}
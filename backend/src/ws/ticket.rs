@@ -0,0 +1,117 @@
+//! WebSocket 接続用の使い捨てチケット
+//! ID トークンを URL (プロキシやアクセスログに残る) に載せないよう、REST で発行したチケットで接続する
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::entities::room::RoomId;
+use crate::entities::user::UserId;
+
+/// チケットの有効期間
+pub const TICKET_TTL: Duration = Duration::from_secs(30);
+
+/// チケットの長さ (英数字)
+const TICKET_LENGTH: usize = 32;
+
+/// 発行済みのチケットが表すもの
+#[derive(Clone, Debug)]
+pub struct Ticket {
+    pub user_id: UserId,
+    /// このルームへの接続にだけ使える
+    pub room_id: RoomId,
+    /// 発行に使った ID トークンの期限 (UNIX 秒)。接続後の再認証の期限になる
+    pub token_expires_at: u64,
+    expires: Instant,
+}
+
+pub struct Tickets {
+    ttl: Duration,
+    tickets: HashMap<String, Ticket>,
+}
+
+impl Default for Tickets {
+    fn default() -> Self {
+        Self::new(TICKET_TTL)
+    }
+}
+
+impl Tickets {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            tickets: HashMap::new(),
+        }
+    }
+
+    /// チケットを発行する。ついでに期限切れのものを捨てる
+    pub fn issue(&mut self, user_id: UserId, room_id: RoomId, token_expires_at: u64) -> String {
+        use rand::{distributions::Alphanumeric, Rng};
+
+        let now = Instant::now();
+        self.tickets.retain(|_, ticket| ticket.expires > now);
+
+        let id: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(TICKET_LENGTH)
+            .map(char::from)
+            .collect();
+        self.tickets.insert(
+            id.clone(),
+            Ticket {
+                user_id,
+                room_id,
+                token_expires_at,
+                expires: now + self.ttl,
+            },
+        );
+        id
+    }
+
+    /// チケットを使う。一度使ったチケットは (失敗しても) 二度と使えない
+    pub fn redeem(&mut self, id: &str) -> Option<Ticket> {
+        let ticket = self.tickets.remove(id)?;
+        (ticket.expires > Instant::now()).then_some(ticket)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{room, user};
+
+    #[test]
+    fn tickets_can_be_redeemed_only_once() {
+        let mut tickets = Tickets::default();
+        let (user_id, room_id) = (user(), room());
+
+        let id = tickets.issue(user_id.clone(), room_id.clone(), 1_700_000_000);
+        assert_eq!(id.len(), TICKET_LENGTH);
+
+        let ticket = tickets.redeem(&id).unwrap();
+        assert_eq!(ticket.user_id, user_id);
+        assert_eq!(ticket.room_id, room_id);
+        assert_eq!(ticket.token_expires_at, 1_700_000_000);
+
+        assert!(tickets.redeem(&id).is_none());
+    }
+
+    #[test]
+    fn expired_tickets_are_rejected_and_purged() {
+        let mut tickets = Tickets::new(Duration::ZERO);
+        let (user_id, room_id) = (user(), room());
+
+        let expired = tickets.issue(user_id.clone(), room_id.clone(), 0);
+        assert!(tickets.redeem(&expired).is_none());
+
+        tickets.issue(user_id.clone(), room_id.clone(), 0);
+        tickets.issue(user_id, room_id, 0);
+        // 発行のたびに期限切れのものは捨てられる
+        assert_eq!(tickets.tickets.len(), 1);
+    }
+
+    #[test]
+    fn unknown_tickets_are_rejected() {
+        let mut tickets = Tickets::default();
+        assert!(tickets.redeem("not-a-ticket").is_none());
+    }
+}
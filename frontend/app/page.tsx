@@ -3,7 +3,7 @@
 import { useAuth } from '@/hooks/useAuth';
 import { useRouter } from 'next/navigation';
 import { useState } from 'react';
-import { createRoom } from '@/lib/api/rooms';
+import { ApiError, createRoom } from '@/lib/api/rooms';
 import type { CreateRoomRequest } from '@/types/generated/create_room_dto';
 
 export default function MenuPage() {
@@ -67,8 +67,13 @@ export default function MenuPage() {
 
     } catch (error: unknown) {
       console.error('ルーム作成エラー:', error);
-      // バックエンドからの一意制約違反（既出のID）などを想定
-      setCreateError('ルームの作成に失敗しました。指定したIDが既に使用されている可能性があります。');
+      if (error instanceof ApiError && error.code === 'SLUG_TAKEN') {
+        setCreateError('指定したIDは既に使用されています。');
+      } else if (error instanceof ApiError && error.code === 'INVALID_SLUG') {
+        setCreateError('IDの形式が正しくありません。');
+      } else {
+        setCreateError('ルームの作成に失敗しました。');
+      }
     } finally {
       setIsCreating(false);
     }
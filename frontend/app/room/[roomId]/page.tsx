@@ -1,11 +1,15 @@
 'use client';
 
 import { useAuth } from '@/hooks/useAuth';
+import { auth } from '@/lib/firebase';
 import { useParams, useRouter } from 'next/navigation';
 import { useEffect, useState, useRef } from 'react';
-import { joinRoom } from '@/lib/api/rooms';
+import { ApiError, createWsTicket, joinRoom } from '@/lib/api/rooms';
 import type { JoinRoomResponse } from '@/types/generated/join_room_response';
+import type { Role } from '@/types/generated/role';
 import type { WsMessagePayload } from '@/types/generated/ws_message';
+import type { ClientEvent } from '@/types/generated/ws_client_event';
+import type { ServerEvent } from '@/types/generated/ws_server_event';
 
 export default function RoomPage() {
   const { user, token, loading: authLoading } = useAuth();
@@ -17,11 +21,18 @@ export default function RoomPage() {
   const [roomData, setRoomData] = useState<JoinRoomResponse | null>(null);
   const [error, setError] = useState<string | null>(null);
   const [loading, setLoading] = useState(true);
+  // 現在の権限 (追加教員の指定・解除で接続中に変わる)
+  const [role, setRole] = useState<Role | null>(null);
+  // ルームが終了しているか (終了中、学生は閲覧のみ)
+  const [isActive, setIsActive] = useState(true);
+  const readOnly = !isActive && role !== 'Teacher';
 
   // 🌟 WebSocket用のステートと参照
   const [messages, setMessages] = useState<WsMessagePayload[]>([]);
   const [inputText, setInputText] = useState('');
   const wsRef = useRef<WebSocket | null>(null);
+  // 再接続時にサーバーへ伝える、最後に受け取ったメッセージのID
+  const lastMessageIdRef = useRef<string | null>(null);
   const messagesEndRef = useRef<HTMLDivElement>(null); // 自動スクロール用
 
   // 【1】部屋の参加検証と情報取得
@@ -36,8 +47,16 @@ export default function RoomPage() {
       try {
         const data = await joinRoom(token, slug);
         setRoomData(data);
+        setRole(data.role);
+        setIsActive(data.room.is_active);
       } catch (err: unknown) {
-        setError('ルームの参加に失敗しました。');
+        if (err instanceof ApiError && err.code === 'ROOM_NOT_FOUND') {
+          setError('ルームが見つかりません。');
+        } else if (err instanceof ApiError && err.code === 'ROOM_ENDED') {
+          setError('このルームは終了しています。');
+        } else {
+          setError('ルームの参加に失敗しました。');
+        }
       } finally {
         setLoading(false);
       }
@@ -51,47 +70,123 @@ export default function RoomPage() {
     // 部屋情報の取得が完了し、トークンがある場合のみ接続を開始
     if (!roomData || !token) return;
 
-    // 現在のURLからWebSocketのURLを判定 (ローカル開発環境と本番環境の切り替え)
-    // ※ API側で 13964 ポートを開いている場合はそれに合わせる
-    const isLocal = window.location.hostname === 'localhost';
-    const wsProtocol = isLocal ? 'ws:' : 'wss:';
-    const wsHost = isLocal ? 'localhost:13964' : 'axon.asappy.xyz'; // サーバーのドメイン
-    const wsUrl = `${wsProtocol}//${wsHost}/api/room/${slug}/ws?token=${token}`;
+    let ws: WebSocket | null = null;
+    let cancelled = false;
 
-    console.log('Connecting to WebSocket:', wsUrl);
-    const ws = new WebSocket(wsUrl);
+    const connect = async () => {
+      // IDトークンをURLに載せないよう、接続ごとに使い捨てのチケットを発行してもらう
+      const { ticket } = await createWsTicket(token, slug);
+      if (cancelled) return;
 
-    // 接続成功時
-    ws.onopen = () => {
-      console.log('✅ WebSocket Connected!');
-    };
+      // 現在のURLからWebSocketのURLを判定 (ローカル開発環境と本番環境の切り替え)
+      // ※ API側で 13964 ポートを開いている場合はそれに合わせる
+      const isLocal = window.location.hostname === 'localhost';
+      const wsProtocol = isLocal ? 'ws:' : 'wss:';
+      const wsHost = isLocal ? 'localhost:13964' : 'axon.asappy.xyz'; // サーバーのドメイン
+      const resumeParam = lastMessageIdRef.current ? `&last_message_id=${lastMessageIdRef.current}` : '';
+      const wsUrl = `${wsProtocol}//${wsHost}/api/room/${slug}/ws?ticket=${ticket}${resumeParam}`;
 
-    // メッセージ受信時
-    ws.onmessage = (event) => {
-      try {
-        const msg = JSON.parse(event.data) as WsMessagePayload;
-        setMessages((prev) => [...prev, msg]);
-      } catch (e) {
-        console.error('Failed to parse message:', e);
-      }
-    };
+      console.log('Connecting to WebSocket:', wsUrl);
+      const socket = new WebSocket(wsUrl);
+      ws = socket;
 
-    // エラー発生時
-    ws.onerror = (err) => {
-      console.error('❌ WebSocket Error:', err);
-    };
+      // 接続成功時
+      socket.onopen = () => {
+        console.log('✅ WebSocket Connected!');
+      };
+
+      // メッセージ受信時
+      socket.onmessage = (event) => {
+        try {
+          const serverEvent = JSON.parse(event.data) as ServerEvent;
+          switch (serverEvent.type) {
+            case 'history':
+              // 接続直後に届く直近の履歴で置き換える
+              setMessages(serverEvent.messages);
+              lastMessageIdRef.current = serverEvent.messages.at(-1)?.id ?? null;
+              break;
+            case 'replay':
+              // 再接続までに届いていた分を後ろに足す
+              setMessages((prev) => [...prev, ...serverEvent.messages]);
+              lastMessageIdRef.current = serverEvent.messages.at(-1)?.id ?? lastMessageIdRef.current;
+              break;
+            case 'resync':
+              // 配信に追いつけなかった。続けて届く replay / history で埋め直す
+              console.warn(`Resyncing after missing ${serverEvent.skipped} events`);
+              break;
+            case 'message':
+            case 'dm':
+              setMessages((prev) => [...prev, serverEvent]);
+              lastMessageIdRef.current = serverEvent.id;
+              break;
+            case 'session_started':
+              // 履歴クリア: 表示中のチャットを空にする (過去のセッションはログとして残る)
+              setMessages([]);
+              lastMessageIdRef.current = null;
+              break;
+            case 'role_changed':
+              // 教員の設定・解除は全員に届くので、自分宛てのものだけ反映する
+              if (serverEvent.user_id === roomData.user_id) {
+                setRole(serverEvent.role);
+              }
+              break;
+            case 'room_status_changed':
+              setIsActive(serverEvent.is_active);
+              break;
+            case 'room_deleted':
+              // この後サーバーから切断される
+              setError('このルームは削除されました。');
+              break;
+            case 'ping': {
+              // サーバーからの疎通確認に応答する (応答がないと切断される)
+              const pong: ClientEvent = { type: 'pong' };
+              socket.send(JSON.stringify(pong));
+              break;
+            }
+            case 'reauth_required':
+              // トークンの期限が近い。新しいトークンを取り直して送る (送らないと期限で切断される)
+              auth.currentUser
+                ?.getIdToken(true)
+                .then((freshToken) => {
+                  const reauth: ClientEvent = { type: 'reauth', token: freshToken };
+                  socket.send(JSON.stringify(reauth));
+                })
+                .catch((e) => console.error('Failed to refresh token:', e));
+              break;
+            case 'error':
+              console.error('Server error:', serverEvent.code, serverEvent.message);
+              break;
+            default:
+              break;
+          }
+        } catch (e) {
+          console.error('Failed to parse message:', e);
+        }
+      };
+
+      // エラー発生時
+      socket.onerror = (err) => {
+        console.error('❌ WebSocket Error:', err);
+      };
+
+      // 接続切断時
+      socket.onclose = () => {
+        console.log('🔌 WebSocket Disconnected');
+      };
 
-    // 接続切断時
-    ws.onclose = () => {
-      console.log('🔌 WebSocket Disconnected');
+      // コンポーネント外から send できるように useRef に保存
+      wsRef.current = socket;
     };
 
-    // コンポーネント外から send できるように useRef に保存
-    wsRef.current = ws;
+    connect().catch((e) => {
+      console.error('Failed to connect WebSocket:', e);
+      setError('チャットへの接続に失敗しました。');
+    });
 
     // クリーンアップ関数: コンポーネントがアンマウントされたら切断する
     return () => {
-      ws.close();
+      cancelled = true;
+      ws?.close();
     };
   }, [roomData, slug, token]);
 
@@ -102,10 +197,11 @@ export default function RoomPage() {
 
   // 【3】メッセージ送信関数
   const handleSendMessage = () => {
-    if (!inputText.trim() || !wsRef.current) return;
+    if (!inputText.trim() || !wsRef.current || readOnly) return;
 
     // WebSocket経由でサーバーに送信！
-    wsRef.current.send(inputText);
+    const event: ClientEvent = { type: 'send_message', content: inputText };
+    wsRef.current.send(JSON.stringify(event));
     setInputText(''); // 送信後は入力欄を空にする
   };
 
@@ -139,8 +235,8 @@ export default function RoomPage() {
             ID: <span className="font-mono bg-gray-100 px-1 rounded">{roomData.room.slug}</span>
             <span className="mx-2">|</span>
             権限:
-            <span className={`ml-1 font-bold ${roomData.role === 'Teacher' ? 'text-red-500' : 'text-blue-500'}`}>
-              {roomData.role === 'Teacher' ? '教員' : '学生'}
+            <span className={`ml-1 font-bold ${role === 'Teacher' ? 'text-red-500' : 'text-blue-500'}`}>
+              {role === 'Teacher' ? '教員' : '学生'}
             </span>
           </p>
         </div>
@@ -211,12 +307,13 @@ export default function RoomPage() {
             value={inputText}
             onChange={(e) => setInputText(e.target.value)}
             onKeyDown={handleKeyDown}
-            placeholder="[全体] メッセージを入力..."
+            disabled={readOnly}
+            placeholder={readOnly ? 'ルームは終了しました (閲覧のみ)' : '[全体] メッセージを入力...'}
             className="flex-1 border border-gray-300 rounded-lg p-3 focus:outline-none focus:ring-2 focus:ring-blue-400 bg-gray-50"
           />
           <button
             onClick={handleSendMessage}
-            disabled={!inputText.trim()}
+            disabled={readOnly || !inputText.trim()}
             className="bg-blue-500 text-white px-6 py-2 rounded-lg font-bold disabled:bg-gray-400 disabled:cursor-not-allowed hover:bg-blue-600 transition"
           >
             送信
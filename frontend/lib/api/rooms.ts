@@ -1,8 +1,33 @@
 import type { CreateRoomRequest } from "@/types/generated/create_room_dto";
+import type { ErrorCode } from "@/types/generated/error_code";
+import type { ErrorResponse } from "@/types/generated/error_response";
 import { JoinRoomResponse } from "@/types/generated/join_room_response";
 import type { Room } from "@/types/generated/room";
+import type { RoomList } from "@/types/generated/room_list";
+import type { RoomScope } from "@/types/generated/room_scope";
+import type { WsTicketResponse } from "@/types/generated/ws_ticket_response";
 import { RoomSchema } from "../schemas/models";
 
+// バックエンドが返したエラー (code で分岐できる)
+export class ApiError extends Error {
+  code: ErrorCode;
+
+  constructor(code: ErrorCode, message: string) {
+    super(message);
+    this.code = code;
+  }
+}
+
+// エラーレスポンスのボディを ApiError にする (JSONでなければ INTERNAL 扱い)
+async function toApiError(res: Response, fallback: string): Promise<ApiError> {
+  try {
+    const body = (await res.json()) as ErrorResponse;
+    return new ApiError(body.code, body.message);
+  } catch {
+    return new ApiError("INTERNAL", fallback);
+  }
+}
+
 export async function createRoom(token: string, payload: CreateRoomRequest): Promise<Room> {
   const res = await fetch("https://axon.asappy.xyz/api/room/create", {
     method: "POST",
@@ -14,7 +39,7 @@ export async function createRoom(token: string, payload: CreateRoomRequest): Pro
   });
 
   if (!res.ok) {
-    throw new Error("Failed to create room");
+    throw await toApiError(res, "Failed to create room");
   }
 
   const data = await res.json();
@@ -34,13 +59,41 @@ export async function joinRoom(token: string, slug: string): Promise<JoinRoomRes
   });
 
   if (!res.ok) {
-    if (res.status === 404) {
-      throw new Error("RoomNotFound");
-    }
-    throw new Error("Failed to join room");
+    throw await toApiError(res, "Failed to join room");
   }
 
   // Zodでパース（水際対策）するのがベストですが、まずは一旦そのまま返して疎通確認します
   const data = await res.json();
   return data as JoinRoomResponse; 
-}
\ No newline at end of file
+}
+
+export async function listRooms(token: string, scope: RoomScope, page = 0): Promise<RoomList> {
+  const params = new URLSearchParams({ scope, page: String(page) });
+  const res = await fetch(`https://axon.asappy.xyz/api/rooms?${params}`, {
+    headers: {
+      "Authorization": `Bearer ${token}`,
+    },
+  });
+
+  if (!res.ok) {
+    throw await toApiError(res, "Failed to list rooms");
+  }
+
+  const data = await res.json();
+  return data as RoomList;
+}
+
+export async function createWsTicket(token: string, slug: string): Promise<WsTicketResponse> {
+  const res = await fetch(`https://axon.asappy.xyz/api/room/${slug}/ws-ticket`, {
+    method: "POST",
+    headers: {
+      "Authorization": `Bearer ${token}`,
+    },
+  });
+
+  if (!res.ok) {
+    throw await toApiError(res, "Failed to issue WebSocket ticket");
+  }
+
+  return (await res.json()) as WsTicketResponse;
+}
@@ -1,7 +1,14 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
+/**
+ * UUIDv7 なので値の大小が送信順と一致し、並び替えやページングのキーにそのまま使える
+ */
 export type MessageId = string;
 
+export type ReactionId = string;
+
 export type RoomId = string;
 
+export type RoomSessionId = string;
+
 export type UserId = string;
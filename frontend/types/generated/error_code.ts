@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * エラーの機械判別用コード (フロントエンドはこれで分岐する)
+ */
+export type ErrorCode = "BAD_REQUEST" | "UNPROCESSABLE_ENTITY" | "UNSUPPORTED_MEDIA_TYPE" | "PAYLOAD_TOO_LARGE" | "UNAUTHORIZED" | "ROOM_NOT_FOUND" | "NOT_A_MEMBER" | "SLUG_TAKEN" | "INVALID_SLUG" | "ROOM_ENDED" | "OWNER_ONLY" | "TEACHER_ONLY" | "SESSION_NOT_FOUND" | "MESSAGE_NOT_FOUND" | "MEMBER_NOT_FOUND" | "CANNOT_DEMOTE_OWNER" | "INVALID_REPLY_PARENT" | "INTERNAL";
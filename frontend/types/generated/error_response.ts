@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ErrorCode } from "./error_code";
+
+/**
+ * エラー時のレスポンスボディ
+ */
+export type ErrorResponse = { code: ErrorCode, message: string, };
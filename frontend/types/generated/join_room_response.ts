@@ -1,5 +1,6 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { Role } from "./role";
 import type { Room } from "./room";
+import type { UserId } from "./branded_types";
 
-export type JoinRoomResponse = { room: Room, role: Role, };
+export type JoinRoomResponse = { room: Room, role: Role, user_id: UserId, };
@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Role } from "./role";
+import type { UserId } from "./branded_types";
+
+/**
+ * メンバー一覧の1人分
+ */
+export type MemberInfo = { user_id: UserId, display_name: string | null, photo_url: string | null, 
+/**
+ * 現在のセッションでの権限
+ */
+role: Role, joined_at: string, 
+/**
+ * WebSocket で接続中か
+ */
+online: boolean, };
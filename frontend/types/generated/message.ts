@@ -1,6 +1,7 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { MessageId } from "./branded_types";
 import type { RoomId } from "./branded_types";
+import type { RoomSessionId } from "./branded_types";
 import type { UserId } from "./branded_types";
 
-export type Message = { id: MessageId, room_id: RoomId, sender_id: UserId, content: string, recipient_id: UserId | null, is_dm: boolean, sent_at: string, };
+export type Message = { id: MessageId, room_id: RoomId, sender_id: UserId, content: string, recipient_id: UserId | null, is_dm: boolean, sent_at: string, parent_message_id: MessageId | null, session_id: RoomSessionId, };
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { WsMessagePayload } from "./ws_message";
+
+/**
+ * 履歴の1ページ (古い順)
+ */
+export type MessageHistory = { messages: Array<WsMessagePayload>, 
+/**
+ * さらに古いメッセージがあるか
+ */
+has_more: boolean, };
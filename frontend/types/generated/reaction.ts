@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MessageId } from "./branded_types";
+import type { ReactionId } from "./branded_types";
+import type { UserId } from "./branded_types";
+
+export type Reaction = { id: ReactionId, message_id: MessageId, user_id: UserId, emoji: string, created_at: string, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { UserId } from "./branded_types";
+
+/**
+ * メッセージに付いたスタンプの集計
+ */
+export type ReactionSummary = { emoji: string, count: number, user_ids: Array<UserId>, };
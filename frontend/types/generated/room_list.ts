@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RoomSummary } from "./room_summary";
+
+/**
+ * ルーム一覧の1ページ (新しく作られた順)
+ */
+export type RoomList = { rooms: Array<RoomSummary>, 
+/**
+ * 次のページがあるか
+ */
+has_more: boolean, };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RoomId } from "./branded_types";
+import type { RoomSessionId } from "./branded_types";
+
+export type RoomSession = { id: RoomSessionId, room_id: RoomId, started_at: string, ended_at: string | null, };
@@ -0,0 +1,21 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Role } from "./role";
+import type { Room } from "./room";
+import type { RoomSession } from "./room_session";
+
+/**
+ * 一覧に表示するルームの情報
+ */
+export type RoomSummary = { room: Room, 
+/**
+ * 現在のセッションでの自分の権限
+ */
+role: Role, member_count: number, 
+/**
+ * 最後にメッセージが送られた日時 (まだなければ null)
+ */
+last_activity_at: string | null, 
+/**
+ * 進行中のセッション (まだ始まっていなければ null)
+ */
+active_session: RoomSession | null, };
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * システムであらかじめ用意しているスタンプ
+ */
+export type Stamp = { 
+/**
+ * reactions.emoji に保存される識別子
+ */
+id: string, label: string, emoji: string, };
@@ -0,0 +1,13 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MessageId } from "./branded_types";
+import type { UserId } from "./branded_types";
+
+/**
+ * クライアント → サーバーのイベント
+ * `type` フィールドで種類を判別するので、種類を増やしても既存の形式は壊れない
+ */
+export type ClientEvent = { "type": "send_message", content: string, 
+/**
+ * クライアント側で採番した一時ID (ack でそのまま返す)
+ */
+client_id?: string, } | { "type": "send_dm", content: string, recipient_id?: UserId, client_id?: string, } | { "type": "reply", parent_message_id: MessageId, content: string, client_id?: string, } | { "type": "react", message_id: MessageId, emoji: string, } | { "type": "unreact", message_id: MessageId, emoji: string, } | { "type": "start_session" } | { "type": "ping" } | { "type": "pong" } | { "type": "reauth", token: string, };
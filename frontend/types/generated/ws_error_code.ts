@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * `ServerEvent::Error` の機械判別用コード
+ */
+export type WsErrorCode = "invalid_payload" | "empty_message" | "recipient_required" | "recipient_not_found" | "student_to_student_dm" | "teacher_to_teacher_dm" | "parent_not_found" | "reply_to_non_dm" | "nested_reply" | "message_not_found" | "invalid_emoji" | "teacher_only" | "room_ended" | "invalid_token" | "internal";
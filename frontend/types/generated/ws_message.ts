@@ -1,4 +1,7 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MessageId } from "./branded_types";
+import type { ReactionSummary } from "./reaction_summary";
 import type { Role } from "./role";
+import type { UserId } from "./branded_types";
 
-export type WsMessagePayload = { id: string, content: string, sender_name: string, sender_photo_url: string | null, sender_role: Role, sent_at: string, };
+export type WsMessagePayload = { id: MessageId, content: string, sender_id: UserId, sender_name: string, sender_photo_url: string | null, sender_role: Role, recipient_id: UserId | null, is_dm: boolean, parent_message_id: MessageId | null, sent_at: string, reactions: Array<ReactionSummary>, };
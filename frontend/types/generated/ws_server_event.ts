@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MemberInfo } from "./member_info";
+import type { MessageHistory } from "./message_history";
+import type { MessageId } from "./branded_types";
+import type { Role } from "./role";
+import type { RoomSession } from "./room_session";
+import type { UserId } from "./branded_types";
+import type { WsErrorCode } from "./ws_error_code";
+import type { WsMessagePayload } from "./ws_message";
+
+/**
+ * サーバー → クライアントのイベント
+ */
+export type ServerEvent = { "type": "history" } & MessageHistory | { "type": "replay", messages: Array<WsMessagePayload>, } | { "type": "resync", skipped: number, } | { "type": "message" } & WsMessagePayload | { "type": "dm" } & WsMessagePayload | { "type": "reaction", message_id: MessageId, emoji: string, user_id: UserId, added: boolean, 
+/**
+ * 変更後のこのスタンプの数
+ */
+count: number, } | { "type": "session_started", session: RoomSession, } | { "type": "member_joined", member: MemberInfo, } | { "type": "member_left", user_id: UserId, } | { "type": "role_changed", user_id: UserId, role: Role, } | { "type": "room_status_changed", is_active: boolean, } | { "type": "room_deleted" } | { "type": "ack", client_id: string | null, message_id: MessageId, } | { "type": "error", code: WsErrorCode, message: string, } | { "type": "pong" } | { "type": "reauth_required", expires_at: number, } | { "type": "reauthenticated", expires_at: number, } | { "type": "ping" };
@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * WebSocket 接続用チケットの発行結果
+ */
+export type WsTicketResponse = { 
+/**
+ * 接続時に `?ticket=` で渡す。一度しか使えない
+ */
+ticket: string, 
+/**
+ * 有効期間 (秒)
+ */
+expires_in: number, };